@@ -0,0 +1,784 @@
+//! 国密算法命令行工具 (无界面)，覆盖 GUI 的 SM3 / SM4 / SM2 / ZUC 四个功能页。
+//!
+//! 参数取值格式:
+//!   - `hex:<HEX>` 或直接写 Hex (key / IV / 数据的默认格式)
+//!   - `HEX:<HEX>` (同 hex，大小写均可)
+//!   - `b64:<BASE64>` / `b64url:<BASE64URL>`
+//!   - `text:<UTF-8 字符串>`
+//!   - `file:<路径>` (读取文件原始字节)
+//!
+//! `--in file:<路径>` 时 SM3 按块流式计算；SM4 / ZUC 加解密再指定 `--out`
+//! 时同样流式处理，不把整个文件读入内存。
+//!
+//! 退出码: 0 成功；1 运算失败 (如 GCM Tag 不匹配、验签失败、校验清单不符、自检未通过)；
+//! 2 参数错误。
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use gm_tools::checksum;
+use gm_tools::kdf;
+use gm_tools::selftest;
+use gm_tools::sm2::{self, CiphertextFormat, SigCtx, SignatureFormat};
+use gm_tools::sm2_exchange::{self, Party};
+use gm_tools::sm2_keycheck;
+use gm_tools::sm2_keyfile::{self, KeyFile, PrivateKeyFormat};
+use gm_tools::sm3;
+use gm_tools::sm4::{self, Sm4Mode};
+use gm_tools::stream::{self, Progress};
+use gm_tools::zuc::{self, MacLen, ZucVariant};
+use gm_tools::{Encoding, GmError};
+use num_bigint::BigUint;
+
+const USAGE: &str = "\
+用法: gm <命令> [参数]
+
+命令:
+  sm3                          计算 SM3 摘要；指定 --key 时计算 HMAC-SM3
+      --in <值> [--key <值> [--tag-len <10..32 字节>] [--expect <值>]]
+                               --expect 与期望的 (可截断) MAC 做常量时间比较
+      --in <值> --bits <消息比特长度>
+                               只对输入的前若干比特计算摘要 (可非整字节)
+  sm3sum <文件或目录>... [--out <清单>]
+                               生成 SM3 校验清单 (每行 `<Hex>  <路径>`)，目录递归展开
+  sm3sum --check <清单> [--base <目录>]
+                               按清单校验 (路径相对 --base，默认当前目录)，逐个输出
+                               OK / FAILED / MISSING，有不通过的文件则退出码为 1
+  sm4 <encrypt|decrypt>        SM4 加解密
+      --mode <ecb|cbc|gcm> --key <值> [--iv <值>] [--aad <值>] [--no-padding] --in <值>
+  sm2 genkey                   生成密钥对 (输出私钥与公钥 Hex)
+  sm2 pubkey  --pri <Hex>          由私钥计算公钥 P = d·G (要求 1 ≤ d ≤ n-2)
+  sm2 sign    --pri <Hex> [--pub <Hex>] --in <值> [--id <值>] [--sig-format <der|raw>] [--k <Hex>]
+                               公钥由私钥计算；给出 --pub 时检查两者匹配；
+                               签名默认 DER，raw 为 64 字节 r||s
+  sm2 verify  --pub <Hex> --in <值> --sig <值> [--id <值>]
+                               --sig 可为 DER 或 64 字节 r||s，自动识别
+  sm2 sigconv --sig <值> --to <der|raw>
+                               签名格式转换，检查 1 ≤ r, s ≤ n-1
+  sm2 za      --pub <Hex> [--id <值>] [--in <值>]
+                               输出 ZA (及给定消息时的 e = SM3(ZA || M))；
+                               --id 为签名者用户 ID，默认 text:1234567812345678
+  sm2 import  --in file:<密钥文件> [--password <值>]
+                               读取 PEM/DER 密钥 (PKCS#8、加密 PKCS#8、SEC1 或 SubjectPublicKeyInfo)，
+                               输出私钥与公钥 Hex；加密 PKCS#8 需要 --password (如 text:口令)
+  sm2 export  --pri <Hex> [--type <pkcs8|sec1>] [--password <值>] [--der]
+  sm2 export  --pub <Hex> [--der]
+                               导出私钥 (默认 PKCS#8 PEM，给出 --password 时用 PBES2
+                               SM4-CBC/HMAC-SM3 加密) 或 SubjectPublicKeyInfo 公钥；
+                               --der 输出 DER (建议配合 --out)
+  sm2 encrypt --pub <Hex> --in <值> [--cipher-format <格式>] [--k <Hex>]
+  sm2 decrypt --pri <Hex> --in <值> [--cipher-format <格式|auto>]
+                               --pub 可为 04||X||Y、压缩的 02/03||X 或 X||Y，须在曲线上；
+                               密文格式为 c1c3c2、c1c2c3 (加密默认)、c1c3c2-bare /
+                               c1c2c3-bare (C1 不带 04) 或 asn1 (GM/T 0009 SM2Cipher)，
+                               解密默认自动识别并在 stderr 输出识别结果
+                               sign / encrypt 的 --k 指定固定随机数 (1 ≤ k ≤ n-1)，结果可复现但不安全，
+                               仅用于复现 GB/T 32918.5 推荐曲线示例或比对设备输出
+  sm2 cipherconv --in <值> --from <格式> --to <格式>
+                               密文格式转换 (没有私钥无法区分 C3/C2 顺序，须给出 --from)
+  sm2 exchange --pri <Hex> --peer-pri <Hex> [--id <值>] [--peer-id <值>]
+               [--ra <Hex>] [--rb <Hex>] [--len <字节>] [--no-confirm]
+                               模拟 GB/T 32918.3 密钥交换：--pri / --id 为发起方 A，
+                               --peer-pri / --peer-id 为响应方 B，--ra / --rb 固定临时私钥
+                               (默认随机)，--len 默认 16；输出 R_A/R_B、U/V、K_A/K_B
+                               及确认值 S1/SB/S2/SA 的全部中间值
+  sm2 check   [--pri <Hex>] [--pub <Hex>]
+                               逐项检查私钥范围、公钥编码、是否在曲线上、是否为无穷远点、
+                               阶是否为 n，两者都给出时检查是否匹配；有不通过的项则退出码为 1
+  zuc <encrypt|decrypt|mac>    ZUC 序列密码 / ZUC-256 MAC
+      --variant <128|256|256-new> --key <值> --iv <值> --in <值>
+      [--mac-len <32|64|128>] [--bits <消息比特长度>]
+  kdf <sm2|pbkdf2|hkdf|hkdf-extract|hkdf-expand>
+      --in <值> [--salt <值>] [--info <值>] [--iter <次数>] [--len <字节>]
+                               密钥派生：--in 为共享秘密 Z / 口令 / IKM / PRK，
+                               --iter 默认 10000，--len 默认 16 (hkdf-extract 固定 32)
+  selftest                     运行全部已知答案测试向量 (SM3/SM4/SM2/ZUC)，任一失败则退出码为 1
+
+通用输出参数:
+  --out <路径>                 将结果原始字节写入文件 (默认以 Hex 打印到 stdout)
+  --format <hex|HEX|b64|b64url|text>
+                               stdout 输出格式 (默认 hex，base64 同 b64)
+
+参数值格式: hex:<..> (默认) | HEX:<..> | b64:<..> | b64url:<..> | text:<..> | file:<路径>
+";
+
+/// Failure of a command, mapped to the process exit code.
+enum CliError {
+    Usage(String),
+    Failed(String),
+}
+
+type CliResult<T> = Result<T, CliError>;
+
+impl From<GmError> for CliError {
+    fn from(e: GmError) -> Self {
+        CliError::Failed(e.to_string())
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(CliError::Failed(msg)) => {
+            eprintln!("错误: {}", msg);
+            ExitCode::from(1)
+        }
+        Err(CliError::Usage(msg)) => {
+            eprintln!("参数错误: {}\n\n{}", msg, USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(args: &[String]) -> CliResult<()> {
+    let Some(cmd) = args.first() else {
+        return Err(CliError::Usage("缺少命令".into()));
+    };
+    match cmd.as_str() {
+        "sm3" => cmd_sm3(&Options::parse(&args[1..], "sm3")?),
+        "sm3sum" => cmd_sm3sum(&args[1..]),
+        "sm4" => cmd_sm4(subcommand(args)?, &sub_options(args)?),
+        "sm2" => cmd_sm2(subcommand(args)?, &sub_options(args)?),
+        "zuc" => cmd_zuc(subcommand(args)?, &sub_options(args)?),
+        "kdf" => cmd_kdf(subcommand(args)?, &sub_options(args)?),
+        "selftest" => cmd_selftest(),
+        "-h" | "--help" | "help" => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        other => Err(CliError::Usage(format!("未知命令: {}", other))),
+    }
+}
+
+fn subcommand(args: &[String]) -> CliResult<&str> {
+    args.get(1)
+        .map(String::as_str)
+        .ok_or_else(|| CliError::Usage(format!("{} 缺少子命令", args[0])))
+}
+
+/// The options after `<命令> <子命令>`.
+fn sub_options(args: &[String]) -> CliResult<Options> {
+    let command = format!("{} {}", args[0], subcommand(args)?);
+    Options::parse(&args[2..], &command)
+}
+
+/// `--out` and `--format`, taken by the commands whose result goes through [`emit`].
+const EMIT_OPTIONS: &[&str] = &["out", "format"];
+
+/// The `--name`s (values and flags) `command` accepts, or `None` if it is unknown.
+fn accepted_options(command: &str) -> Option<(&'static [&'static str], bool)> {
+    let (names, emits): (&[&str], bool) = match command {
+        "sm3" => (&["in", "key", "tag-len", "expect", "bits"], true),
+        "sm3sum" => (&["out", "check", "base"], false),
+        "sm4 encrypt" | "sm4 decrypt" => (&["mode", "key", "iv", "aad", "no-padding", "in"], true),
+        "sm2 genkey" => (&[], false),
+        "sm2 pubkey" => (&["pri"], false),
+        "sm2 sign" => (&["pri", "pub", "in", "id", "sig-format", "k"], true),
+        "sm2 verify" => (&["pub", "in", "sig", "id"], false),
+        "sm2 sigconv" => (&["sig", "to"], true),
+        "sm2 za" => (&["pub", "id", "in"], false),
+        "sm2 import" => (&["in", "password"], false),
+        "sm2 export" => (&["pri", "pub", "type", "password", "der"], true),
+        "sm2 encrypt" => (&["pub", "in", "cipher-format", "k"], true),
+        "sm2 decrypt" => (&["pri", "in", "cipher-format"], true),
+        "sm2 cipherconv" => (&["in", "from", "to"], true),
+        "sm2 exchange" => (
+            &[
+                "pri",
+                "peer-pri",
+                "id",
+                "peer-id",
+                "ra",
+                "rb",
+                "len",
+                "no-confirm",
+            ],
+            false,
+        ),
+        "sm2 check" => (&["pri", "pub"], false),
+        "zuc encrypt" | "zuc decrypt" => (&["variant", "key", "iv", "in"], true),
+        "zuc mac" => (&["variant", "key", "iv", "in", "mac-len", "bits"], true),
+        "kdf sm2" => (&["in", "len"], true),
+        "kdf pbkdf2" => (&["in", "salt", "iter", "len"], true),
+        "kdf hkdf" => (&["in", "salt", "info", "len"], true),
+        "kdf hkdf-extract" => (&["in", "salt"], true),
+        "kdf hkdf-expand" => (&["in", "info", "len"], true),
+        _ => return None,
+    };
+    Some((names, emits))
+}
+
+/// `--name value` pairs plus boolean `--flag`s.
+struct Options {
+    values: HashMap<String, String>,
+    flags: Vec<String>,
+}
+
+const FLAGS: &[&str] = &["no-padding", "der", "no-confirm"];
+
+impl Options {
+    /// Parse the options of `command` (`sm3`, `sm2 sign`, ..), rejecting
+    /// ones it does not accept and ones given twice.
+    fn parse(args: &[String], command: &str) -> CliResult<Self> {
+        let Some((names, emits)) = accepted_options(command) else {
+            let (cmd, sub) = command.split_once(' ').unwrap_or((command, ""));
+            return Err(CliError::Usage(format!("未知 {} 子命令: {}", cmd, sub)));
+        };
+        let mut values = HashMap::new();
+        let mut flags = Vec::new();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                return Err(CliError::Usage(format!("无法识别的参数: {}", arg)));
+            };
+            let accepted = names.contains(&name) || (emits && EMIT_OPTIONS.contains(&name));
+            if !accepted {
+                return Err(CliError::Usage(format!(
+                    "{} 不支持参数 --{}",
+                    command, name
+                )));
+            }
+            if values.contains_key(name) || flags.iter().any(|f| f == name) {
+                return Err(CliError::Usage(format!("参数 --{} 重复出现", name)));
+            }
+            if FLAGS.contains(&name) {
+                flags.push(name.to_string());
+                continue;
+            }
+            let value = iter
+                .next()
+                .ok_or_else(|| CliError::Usage(format!("--{} 缺少取值", name)))?;
+            values.insert(name.to_string(), value.clone());
+        }
+        Ok(Self { values, flags })
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|f| f == name)
+    }
+
+    fn str(&self, name: &str) -> CliResult<&str> {
+        self.values
+            .get(name)
+            .map(String::as_str)
+            .ok_or_else(|| CliError::Usage(format!("缺少参数 --{}", name)))
+    }
+
+    fn opt_str(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// Decode a `hex:` / `b64:` / `text:` / `file:` value into bytes.
+    fn bytes(&self, name: &str) -> CliResult<Vec<u8>> {
+        decode_value(name, self.str(name)?)
+    }
+
+    fn opt_bytes(&self, name: &str) -> CliResult<Option<Vec<u8>>> {
+        self.opt_str(name)
+            .map(|v| decode_value(name, v))
+            .transpose()
+    }
+}
+
+/// A malformed value is a usage error; an unreadable `file:` is a failure.
+fn decode_value(name: &str, value: &str) -> CliResult<Vec<u8>> {
+    let (enc, v) = Encoding::split_tagged(value, Encoding::Hex);
+    enc.decode("参数", v).map_err(|e| match e {
+        GmError::InvalidEncoding { encoding, .. } => {
+            CliError::Usage(format!("--{} 不是合法的 {}", name, encoding))
+        }
+        other => other.into(),
+    })
+}
+
+/// Write the result to `--out` as raw bytes, or print it to stdout.
+fn emit(opts: &Options, data: &[u8]) -> CliResult<()> {
+    if let Some(path) = opts.opt_str("out") {
+        return std::fs::write(path, data)
+            .map_err(|e| CliError::Failed(format!("写入文件 {} 失败: {}", path, e)));
+    }
+    let text = match opts.opt_str("format").unwrap_or("hex") {
+        "base64" => Encoding::Base64.encode(data)?,
+        "text" => String::from_utf8_lossy(data).into_owned(),
+        tag => match Encoding::from_tag(tag) {
+            Some(enc) if enc != Encoding::File => enc.encode(data)?,
+            _ => return Err(CliError::Usage(format!("未知输出格式: {}", tag))),
+        },
+    };
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "{}", text).map_err(|e| CliError::Failed(e.to_string()))
+}
+
+/// The `--in` file, when the input should be streamed rather than read into memory.
+fn stream_input(opts: &Options) -> CliResult<Option<PathBuf>> {
+    let (enc, value) = Encoding::split_tagged(opts.str("in")?, Encoding::Hex);
+    Ok((enc == Encoding::File).then(|| PathBuf::from(value)))
+}
+
+/// Stream the `--in` file to the `--out` file if both are files.
+fn stream_file_to_file(
+    opts: &Options,
+    f: impl FnOnce(File, &mut BufWriter<File>, &Progress) -> gm_tools::Result<()>,
+) -> CliResult<bool> {
+    let (Some(input), Some(output)) = (stream_input(opts)?, opts.opt_str("out")) else {
+        return Ok(false);
+    };
+    stream::file_to_file(&input, Path::new(output), &Progress::default(), f)?;
+    Ok(true)
+}
+
+fn cmd_selftest() -> CliResult<()> {
+    let reports = selftest::run_all(&SigCtx::new());
+    for report in &reports {
+        match &report.outcome {
+            Ok(()) => println!("[通过] {} ({})", report.name, report.source),
+            Err(e) => println!("[失败] {} ({}): {}", report.name, report.source, e),
+        }
+    }
+    let failed = reports.iter().filter(|r| !r.passed()).count();
+    println!(
+        "共 {} 项，通过 {}，失败 {}",
+        reports.len(),
+        reports.len() - failed,
+        failed
+    );
+    match failed {
+        0 => Ok(()),
+        _ => Err(CliError::Failed(format!("自检未通过 ({} 项失败)", failed))),
+    }
+}
+
+fn cmd_sm3(opts: &Options) -> CliResult<()> {
+    if let Some(bits) = opts.opt_str("bits") {
+        if opts.opt_str("key").is_some() {
+            return Err(CliError::Usage("--bits 不能与 --key 同时使用".into()));
+        }
+        let bits = bits
+            .parse()
+            .map_err(|_| CliError::Usage("--bits 必须是整数".into()))?;
+        return emit(opts, &sm3::digest_bits(&opts.bytes("in")?, bits)?);
+    }
+    let Some(key) = opts.opt_bytes("key")? else {
+        if let Some(path) = stream_input(opts)? {
+            let progress = Progress::default();
+            let file = stream::open_input(&path, &progress)?;
+            return emit(opts, &sm3::digest_reader(file, &progress)?);
+        }
+        let data = opts.bytes("in")?;
+        return emit(opts, &sm3::digest(&data));
+    };
+
+    let mac = match stream_input(opts)? {
+        Some(path) => {
+            let progress = Progress::default();
+            let file = stream::open_input(&path, &progress)?;
+            sm3::hmac_reader(&key, file, &progress)?
+        }
+        None => sm3::hmac(&key, &opts.bytes("in")?),
+    };
+    if let Some(expected) = opts.opt_bytes("expect")? {
+        return match sm3::hmac_verify(&mac, &expected)? {
+            true => {
+                println!("HMAC 校验: 通过 (Valid)");
+                Ok(())
+            }
+            false => Err(CliError::Failed("HMAC 校验: 失败 (Invalid)".into())),
+        };
+    }
+    let tag_len = match opts.opt_str("tag-len") {
+        Some(n) => n
+            .parse()
+            .map_err(|_| CliError::Usage("--tag-len 必须是整数".into()))?,
+        None => sm3::DIGEST_LEN,
+    };
+    emit(opts, &sm3::truncate_tag(&mac, tag_len)?)
+}
+
+/// `sm3sum` takes the files and directories to hash as leading positional
+/// arguments, followed by the usual `--name value` options.
+fn cmd_sm3sum(args: &[String]) -> CliResult<()> {
+    let split = args
+        .iter()
+        .position(|a| a.starts_with("--"))
+        .unwrap_or(args.len());
+    let (inputs, rest) = args.split_at(split);
+    let opts = Options::parse(rest, "sm3sum")?;
+    let progress = Progress::default();
+
+    if let Some(manifest) = opts.opt_str("check") {
+        if !inputs.is_empty() {
+            return Err(CliError::Usage("--check 不能与文件参数同时使用".into()));
+        }
+        let text = std::fs::read_to_string(manifest)
+            .map_err(|e| CliError::Failed(format!("读取清单 {} 失败: {}", manifest, e)))?;
+        let entries = checksum::parse_manifest(&text)?;
+        let base = Path::new(opts.opt_str("base").unwrap_or("."));
+        let results = checksum::verify(base, &entries, &progress)?;
+        for checked in &results {
+            println!("{}: {}", checked.path, checked.status.label());
+        }
+        let (ok, failed, missing) = checksum::tally(&results);
+        println!(
+            "共 {} 个文件，OK {}，FAILED {}，MISSING {}",
+            results.len(),
+            ok,
+            failed,
+            missing
+        );
+        return match failed + missing {
+            0 => Ok(()),
+            n => Err(CliError::Failed(format!("{} 个文件未通过校验", n))),
+        };
+    }
+
+    if inputs.is_empty() {
+        return Err(CliError::Usage("sm3sum 缺少文件或目录".into()));
+    }
+    let mut paths = Vec::new();
+    for input in inputs {
+        if Path::new(input).is_dir() {
+            let dir = input.trim_end_matches(['/', '\\']);
+            for rel in checksum::scan_dir(Path::new(input))? {
+                paths.push(format!("{}/{}", dir, rel));
+            }
+        } else {
+            paths.push(input.clone());
+        }
+    }
+    let manifest =
+        checksum::format_manifest(&checksum::generate(Path::new("."), &paths, &progress)?);
+    match opts.opt_str("out") {
+        Some(path) => std::fs::write(path, manifest)
+            .map_err(|e| CliError::Failed(format!("写入文件 {} 失败: {}", path, e))),
+        None => {
+            print!("{}", manifest);
+            Ok(())
+        }
+    }
+}
+
+fn cmd_sm4(action: &str, opts: &Options) -> CliResult<()> {
+    let encrypt = match action {
+        "encrypt" => true,
+        "decrypt" => false,
+        other => return Err(CliError::Usage(format!("未知 sm4 子命令: {}", other))),
+    };
+    let mode = opts.str("mode")?;
+    let mode = Sm4Mode::from_tag(mode)
+        .ok_or_else(|| CliError::Usage(format!("未知 SM4 模式: {}", mode)))?;
+    let key = opts.bytes("key")?;
+    let iv = match mode {
+        Sm4Mode::ECB => Vec::new(),
+        Sm4Mode::CBC | Sm4Mode::GCM => opts.bytes("iv")?,
+    };
+    let aad = opts.opt_bytes("aad")?.unwrap_or_default();
+    let padding = !opts.flag("no-padding");
+
+    if stream_file_to_file(opts, |r, w, p| {
+        sm4::crypt_stream(mode, &key, &iv, &aad, encrypt, padding, r, w, p)
+    })? {
+        return Ok(());
+    }
+
+    let data = opts.bytes("in")?;
+    let result = match mode {
+        Sm4Mode::ECB if encrypt => sm4::ecb_encrypt(&key, &data, padding),
+        Sm4Mode::ECB => sm4::ecb_decrypt(&key, &data, padding),
+        Sm4Mode::CBC if encrypt => sm4::cbc_encrypt(&key, &iv, &data, padding),
+        Sm4Mode::CBC => sm4::cbc_decrypt(&key, &iv, &data, padding),
+        Sm4Mode::GCM if encrypt => sm4::gcm_encrypt(&key, &iv, &aad, &data),
+        Sm4Mode::GCM => sm4::gcm_decrypt(&key, &iv, &aad, &data),
+    };
+    emit(opts, &result?)
+}
+
+fn cmd_sm2(action: &str, opts: &Options) -> CliResult<()> {
+    let ctx = SigCtx::new();
+    match action {
+        "genkey" => {
+            let (pk, sk) = sm2::generate_keypair(&ctx)?;
+            let pk_hex = sm2::public_key_to_hex(&ctx, &pk)?;
+            println!("private: {}", sm2::private_key_to_hex(&sk));
+            println!("public:  {}", pk_hex);
+            Ok(())
+        }
+        "pubkey" => {
+            let sk = load_private_key(opts)?;
+            let pk = sm2::public_key_from_private(&sk)?;
+            println!("{}", sm2::public_key_to_hex(&ctx, &pk)?);
+            Ok(())
+        }
+        "sign" => {
+            let sk = load_private_key(opts)?;
+            let pk = sm2::public_key_from_private(&sk)?;
+            if opts.opt_str("pub").is_some() {
+                let given = load_public_key(&ctx, opts)?;
+                if sm2::public_key_to_hex(&ctx, &given)? != sm2::public_key_to_hex(&ctx, &pk)? {
+                    return Err(CliError::Usage("--pub 与 --pri 不是同一密钥对".into()));
+                }
+            }
+            let format = match opts.opt_str("sig-format") {
+                Some(tag) => signature_format(tag)?,
+                None => SignatureFormat::Der,
+            };
+            let (id, msg) = (user_id(opts)?, opts.bytes("in")?);
+            let der = match fixed_k(opts)? {
+                Some(k) => sm2::sign_with_k(&id, &msg, &sk, &pk, &k)?,
+                None => sm2::sign(&ctx, &id, &msg, &sk, &pk)?,
+            };
+            emit(opts, &sm2::convert_signature(&der, format)?)
+        }
+        "sigconv" => {
+            let format = signature_format(opts.str("to")?)?;
+            emit(opts, &sm2::convert_signature(&opts.bytes("sig")?, format)?)
+        }
+        "verify" => {
+            let pk = load_public_key(&ctx, opts)?;
+            let sig = opts.bytes("sig")?;
+            match sm2::verify(&ctx, &user_id(opts)?, &opts.bytes("in")?, &pk, &sig)? {
+                true => {
+                    println!("验签结果: 通过 (Valid)");
+                    Ok(())
+                }
+                false => Err(CliError::Failed("验签结果: 失败 (Invalid)".into())),
+            }
+        }
+        "import" => {
+            let data = opts.bytes("in")?;
+            let password = opts.opt_bytes("password")?;
+            match sm2_keyfile::decode(&ctx, &data, password.as_deref())? {
+                KeyFile::Private(sk) => {
+                    let pk = sm2::public_key_from_private(&sk)?;
+                    println!("private: {}", sm2::private_key_to_hex(&sk));
+                    println!("public:  {}", sm2::public_key_to_hex(&ctx, &pk)?);
+                }
+                KeyFile::Public(pk) => println!("public:  {}", sm2::public_key_to_hex(&ctx, &pk)?),
+            }
+            Ok(())
+        }
+        "export" => {
+            let pem = !opts.flag("der");
+            let data = if opts.opt_str("pri").is_some() {
+                let format = match opts.opt_str("type") {
+                    None => PrivateKeyFormat::Pkcs8,
+                    Some(tag) => PrivateKeyFormat::from_tag(tag).ok_or_else(|| {
+                        CliError::Usage(format!("未知私钥格式: {} (应为 pkcs8 或 sec1)", tag))
+                    })?,
+                };
+                let password = opts.opt_bytes("password")?;
+                let sk = load_private_key(opts)?;
+                sm2_keyfile::encode_private_key(&ctx, &sk, format, password.as_deref(), pem)?
+            } else {
+                sm2_keyfile::encode_public_key(&ctx, &load_public_key(&ctx, opts)?, pem)?
+            };
+            match (pem, opts.opt_str("out")) {
+                (true, None) => {
+                    print!("{}", String::from_utf8_lossy(&data));
+                    Ok(())
+                }
+                _ => emit(opts, &data),
+            }
+        }
+        "za" => {
+            let pk = load_public_key(&ctx, opts)?;
+            let za = sm2::za(&pk, &user_id(opts)?)?;
+            println!("ZA: {}", hex::encode(za));
+            if let Some(msg) = opts.opt_bytes("in")? {
+                println!("e:  {}", hex::encode(sm2::message_hash(&za, &msg)));
+            }
+            Ok(())
+        }
+        "encrypt" => {
+            let pk = load_public_key(&ctx, opts)?;
+            let format = match opts.opt_str("cipher-format") {
+                Some(tag) => ciphertext_format(tag)?,
+                None => CiphertextFormat::default(),
+            };
+            let msg = opts.bytes("in")?;
+            let ciphertext = match fixed_k(opts)? {
+                Some(k) => sm2::encrypt_with_k(&pk, &msg, &k, format)?,
+                None => sm2::encrypt(&pk, &msg, format)?,
+            };
+            emit(opts, &ciphertext)
+        }
+        "decrypt" => {
+            let sk = load_private_key(opts)?;
+            let format = match opts.opt_str("cipher-format") {
+                None | Some("auto") => None,
+                Some(tag) => Some(ciphertext_format(tag)?),
+            };
+            let out = sm2::decrypt(&sk, &opts.bytes("in")?, format)?;
+            if format.is_none() {
+                eprintln!("密文格式: {}", out.format.label());
+            }
+            emit(opts, &out.plaintext)
+        }
+        "cipherconv" => {
+            let from = ciphertext_format(opts.str("from")?)?;
+            let to = ciphertext_format(opts.str("to")?)?;
+            emit(
+                opts,
+                &sm2::convert_ciphertext(&opts.bytes("in")?, from, to)?,
+            )
+        }
+        "exchange" => {
+            let party = |pri: &str, id: &str, r: &str, name: &str| -> CliResult<Party> {
+                let sk = sm2::parse_private_key(opts.str(pri)?)
+                    .map_err(|e| CliError::Failed(format!("--{}: {}", pri, e)))?;
+                Ok(Party {
+                    id: opts
+                        .opt_bytes(id)?
+                        .unwrap_or_else(|| sm2::DEFAULT_USER_ID.as_bytes().to_vec()),
+                    pk: sm2::public_key_from_private(&sk)?,
+                    sk,
+                    r: sm2_exchange::parse_ephemeral_key(opts.opt_str(r).unwrap_or(""), name)?,
+                })
+            };
+            let a = party("pri", "id", "ra", "r_A")?;
+            let b = party("peer-pri", "peer-id", "rb", "r_B")?;
+            let len = match opts.opt_str("len") {
+                Some(n) => n
+                    .parse()
+                    .map_err(|_| CliError::Usage("--len 必须是整数".into()))?,
+                None => 16,
+            };
+            let result = sm2_exchange::exchange(&a, &b, len)?;
+            print!("{}", result.to_text(!opts.flag("no-confirm")));
+            match result.agreed() {
+                true => Ok(()),
+                false => Err(CliError::Failed("双方协商结果不一致".into())),
+            }
+        }
+        "check" => {
+            let report = sm2_keycheck::check_keys(
+                &ctx,
+                opts.opt_str("pri").unwrap_or(""),
+                opts.opt_str("pub").unwrap_or(""),
+            )
+            .map_err(|e| CliError::Usage(e.to_string()))?;
+            println!("{}", report.to_text());
+            match report.passed() {
+                true => Ok(()),
+                false => Err(CliError::Failed("密钥检查未通过".into())),
+            }
+        }
+        other => Err(CliError::Usage(format!("未知 sm2 子命令: {}", other))),
+    }
+}
+
+fn load_private_key(opts: &Options) -> CliResult<BigUint> {
+    Ok(sm2::parse_private_key(opts.str("pri")?)?)
+}
+
+/// `--id`, or the default user ID when absent.
+fn user_id(opts: &Options) -> CliResult<Vec<u8>> {
+    Ok(opts
+        .opt_bytes("id")?
+        .unwrap_or_else(|| sm2::DEFAULT_USER_ID.as_bytes().to_vec()))
+}
+
+/// `--k`, the insecure fixed random number for reproducing test vectors.
+fn fixed_k(opts: &Options) -> CliResult<Option<BigUint>> {
+    let Some(k) = opts.opt_str("k") else {
+        return Ok(None);
+    };
+    let k = sm2::parse_k(k)?;
+    eprintln!("警告: 使用固定随机数 k，结果不安全，仅用于复现测试向量");
+    Ok(Some(k))
+}
+
+fn signature_format(tag: &str) -> CliResult<SignatureFormat> {
+    SignatureFormat::from_tag(tag)
+        .ok_or_else(|| CliError::Usage(format!("未知签名格式: {} (应为 der 或 raw)", tag)))
+}
+
+fn ciphertext_format(tag: &str) -> CliResult<CiphertextFormat> {
+    CiphertextFormat::from_tag(tag).ok_or_else(|| {
+        CliError::Usage(format!(
+            "未知密文格式: {} (应为 c1c3c2、c1c2c3、c1c3c2-bare、c1c2c3-bare 或 asn1)",
+            tag
+        ))
+    })
+}
+
+fn load_public_key(ctx: &SigCtx, opts: &Options) -> CliResult<sm2::Point> {
+    Ok(sm2::parse_public_key(ctx, opts.str("pub")?)?)
+}
+
+fn cmd_zuc(action: &str, opts: &Options) -> CliResult<()> {
+    let variant = match opts.str("variant")? {
+        "128" => ZucVariant::Zuc128,
+        "256" => ZucVariant::Zuc256,
+        "256-new" => ZucVariant::Zuc256New,
+        other => return Err(CliError::Usage(format!("未知 ZUC 版本: {}", other))),
+    };
+    let key = opts.bytes("key")?;
+    let iv = opts.bytes("iv")?;
+
+    match action {
+        "encrypt" | "decrypt" => {
+            if stream_file_to_file(opts, |r, w, p| {
+                zuc::apply_keystream_stream(variant, &key, &iv, r, w, p)
+            })? {
+                return Ok(());
+            }
+            let mut data = opts.bytes("in")?;
+            zuc::apply_keystream(variant, &key, &iv, &mut data)?;
+            emit(opts, &data)
+        }
+        "mac" => {
+            let data = opts.bytes("in")?;
+            if variant != ZucVariant::Zuc256New {
+                return Err(CliError::Usage("MAC 仅支持 --variant 256-new".into()));
+            }
+            let mac_len = opts.opt_str("mac-len").unwrap_or("32");
+            let mac_len = mac_len
+                .parse()
+                .ok()
+                .and_then(MacLen::from_bits)
+                .ok_or_else(|| CliError::Usage(format!("未知 MAC 长度: {}", mac_len)))?;
+            let bits = match opts.opt_str("bits") {
+                Some(b) => b
+                    .parse()
+                    .map_err(|_| CliError::Usage("--bits 必须是整数".into()))?,
+                None => data.len() * 8,
+            };
+            let tag = zuc::zuc256_mac(&key, &iv, &data, bits, mac_len)?;
+            emit(opts, &tag)
+        }
+        other => Err(CliError::Usage(format!("未知 zuc 子命令: {}", other))),
+    }
+}
+
+fn cmd_kdf(action: &str, opts: &Options) -> CliResult<()> {
+    let secret = opts.bytes("in")?;
+    let salt = opts.opt_bytes("salt")?.unwrap_or_default();
+    let info = opts.opt_bytes("info")?.unwrap_or_default();
+    let len = match opts.opt_str("len") {
+        Some(n) => n
+            .parse()
+            .map_err(|_| CliError::Usage("--len 必须是整数".into()))?,
+        None => 16,
+    };
+    let key = match action {
+        "sm2" => kdf::sm2_kdf(&secret, len)?,
+        "pbkdf2" => {
+            let iterations = match opts.opt_str("iter") {
+                Some(n) => n
+                    .parse()
+                    .map_err(|_| CliError::Usage("--iter 必须是整数".into()))?,
+                None => 10_000,
+            };
+            kdf::pbkdf2_hmac_sm3(&secret, &salt, iterations, len)?
+        }
+        "hkdf" => kdf::hkdf(&salt, &secret, &info, len)?,
+        "hkdf-extract" => kdf::hkdf_extract(&salt, &secret).to_vec(),
+        "hkdf-expand" => kdf::hkdf_expand(&secret, &info, len)?,
+        other => return Err(CliError::Usage(format!("未知 kdf 子命令: {}", other))),
+    };
+    emit(opts, &key)
+}
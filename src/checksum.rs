@@ -0,0 +1,216 @@
+//! SM3 校验清单：`sm3sum` / `sha256sum` 风格的 `<Hex>  <路径>` 文本
+
+use std::fs::File;
+use std::path::Path;
+
+use crate::error::{GmError, Result};
+use crate::sm3::{self, DIGEST_LEN};
+use crate::stream::{self, Progress};
+
+/// One manifest line: a file path (relative to the manifest base, `/`
+/// separated) and its SM3 digest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub digest: [u8; DIGEST_LEN],
+    pub path: String,
+}
+
+/// Outcome of checking one manifest entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Failed,
+    Missing,
+}
+
+impl Status {
+    /// The word `sm3sum -c` prints for this outcome.
+    pub fn label(self) -> &'static str {
+        match self {
+            Status::Ok => "OK",
+            Status::Failed => "FAILED",
+            Status::Missing => "MISSING",
+        }
+    }
+}
+
+/// Result of [`verify`] for one entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checked {
+    pub path: String,
+    pub status: Status,
+}
+
+/// Number of `(ok, failed, missing)` results.
+pub fn tally(results: &[Checked]) -> (usize, usize, usize) {
+    let count = |s| results.iter().filter(|c| c.status == s).count();
+    (
+        count(Status::Ok),
+        count(Status::Failed),
+        count(Status::Missing),
+    )
+}
+
+/// Every regular file below `dir`, as sorted `/` separated paths relative to it.
+///
+/// Symbolic links to directories are not followed.
+pub fn scan_dir(dir: &Path) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    scan_into(dir, "", &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+fn scan_into(dir: &Path, prefix: &str, files: &mut Vec<String>) -> Result<()> {
+    let entries = std::fs::read_dir(dir).map_err(|e| stream::io_error(dir, e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| stream::io_error(dir, e))?;
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        let path = entry.path();
+        let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+        if is_dir {
+            scan_into(&path, &format!("{}/", name), files)?;
+        } else if path.is_file() {
+            files.push(name);
+        }
+    }
+    Ok(())
+}
+
+/// Hash the files `paths` (relative to `base`) for a new manifest.
+///
+/// The progress total is the combined size of all files.
+pub fn generate(base: &Path, paths: &[String], progress: &Progress) -> Result<Vec<Entry>> {
+    progress.set_total(total_size(base, paths));
+    paths
+        .iter()
+        .map(|path| {
+            let full = base.join(path);
+            let file = File::open(&full).map_err(|e| stream::io_error(&full, e))?;
+            Ok(Entry {
+                digest: sm3::digest_reader(file, progress)?,
+                path: path.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Re-hash every entry's file under `base` and compare it with the manifest.
+///
+/// Files that cannot be opened are reported as [`Status::Missing`] rather
+/// than aborting the whole check.
+pub fn verify(base: &Path, entries: &[Entry], progress: &Progress) -> Result<Vec<Checked>> {
+    let paths: Vec<String> = entries.iter().map(|e| e.path.clone()).collect();
+    progress.set_total(total_size(base, &paths));
+    entries
+        .iter()
+        .map(|entry| {
+            let status = match File::open(base.join(&entry.path)) {
+                Err(_) => Status::Missing,
+                Ok(file) => match sm3::digest_reader(file, progress)? == entry.digest {
+                    true => Status::Ok,
+                    false => Status::Failed,
+                },
+            };
+            Ok(Checked {
+                path: entry.path.clone(),
+                status,
+            })
+        })
+        .collect()
+}
+
+fn total_size(base: &Path, paths: &[String]) -> u64 {
+    paths
+        .iter()
+        .filter_map(|p| std::fs::metadata(base.join(p)).ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Render entries as manifest text, one `<hex>  <path>` line each.
+pub fn format_manifest(entries: &[Entry]) -> String {
+    entries
+        .iter()
+        .map(|e| format!("{}  {}\n", hex::encode(e.digest), e.path))
+        .collect()
+}
+
+/// Parse manifest text. Blank lines are skipped, the digest may be in either
+/// case and a `*` before the path (binary mode marker) is accepted.
+pub fn parse_manifest(text: &str) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let invalid = |detail: &str| GmError::InvalidManifest {
+            line: i + 1,
+            detail: detail.into(),
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (digest_hex, rest) = line
+            .split_once(' ')
+            .ok_or_else(|| invalid("缺少文件路径"))?;
+        let path = rest
+            .strip_prefix(' ')
+            .or_else(|| rest.strip_prefix('*'))
+            .ok_or_else(|| invalid("摘要与路径之间应为两个空格或 \" *\""))?;
+        if path.is_empty() {
+            return Err(invalid("缺少文件路径"));
+        }
+        let mut digest = [0u8; DIGEST_LEN];
+        hex::decode_to_slice(digest_hex, &mut digest).map_err(|_| invalid("摘要不是 64 位 Hex"))?;
+        entries.push(Entry {
+            digest,
+            path: path.to_string(),
+        });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_and_verify_tree() {
+        let dir = std::env::temp_dir().join(format!("gm_tools_checksum_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("a.txt"), "abc").unwrap();
+        std::fs::write(dir.join("sub/b.bin"), [0u8; 100]).unwrap();
+        std::fs::write(dir.join("sub/c.bin"), "").unwrap();
+
+        let paths = scan_dir(&dir).unwrap();
+        assert_eq!(paths, ["a.txt", "sub/b.bin", "sub/c.bin"]);
+        let entries = generate(&dir, &paths, &Progress::default()).unwrap();
+        let text = format_manifest(&entries);
+        assert!(text.starts_with(
+            "66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0  a.txt\n"
+        ));
+        assert_eq!(parse_manifest(&text).unwrap(), entries);
+
+        std::fs::write(dir.join("sub/b.bin"), [1u8; 100]).unwrap();
+        std::fs::remove_file(dir.join("sub/c.bin")).unwrap();
+        let results = verify(&dir, &entries, &Progress::default()).unwrap();
+        let statuses: Vec<Status> = results.iter().map(|c| c.status).collect();
+        assert_eq!(statuses, [Status::Ok, Status::Failed, Status::Missing]);
+        assert_eq!(tally(&results), (1, 1, 1));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_manifest_formats() {
+        let digest = "66C7F0F462EEEDD9D1F2D46BDC10E4E24167C4875CF2F7A2297DA02B8F4BA8E0";
+        let text = format!("{} *bin/x y.zip\n\n{}  z\n", digest, digest.to_lowercase());
+        let entries = parse_manifest(&text).unwrap();
+        assert_eq!(entries[0].path, "bin/x y.zip");
+        assert_eq!(entries[1].path, "z");
+        assert_eq!(entries[0].digest, sm3::digest(b"abc"));
+
+        let err = parse_manifest("abcd  file\n").unwrap_err();
+        assert!(matches!(err, GmError::InvalidManifest { line: 1, .. }));
+        let err = parse_manifest(&format!("\n{} file", digest)).unwrap_err();
+        assert!(matches!(err, GmError::InvalidManifest { line: 2, .. }));
+    }
+}
@@ -0,0 +1,189 @@
+//! 最小的 DER 编解码：密钥文件与 ASN.1 格式的 SM2 数据只用到少数几种类型
+
+use crate::error::{GmError, Result};
+
+pub const INTEGER: u8 = 0x02;
+pub const BIT_STRING: u8 = 0x03;
+pub const OCTET_STRING: u8 = 0x04;
+pub const NULL: u8 = 0x05;
+pub const OID: u8 = 0x06;
+pub const SEQUENCE: u8 = 0x30;
+
+/// Tag of the constructed, context-specific `[n]`.
+pub const fn context(n: u8) -> u8 {
+    0xa0 | n
+}
+
+fn invalid(detail: impl Into<String>) -> GmError {
+    GmError::InvalidDer(detail.into())
+}
+
+/// Sequential reader over the contents of one constructed value.
+#[derive(Clone)]
+pub struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Read `data` as exactly one `SEQUENCE` and return a reader over its contents.
+    pub fn top_sequence(data: &'a [u8]) -> Result<Self> {
+        let mut outer = Self::new(data);
+        let inner = outer.sequence()?;
+        outer.finish()?;
+        Ok(inner)
+    }
+
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Read the next value, whatever its tag.
+    pub fn read_any(&mut self) -> Result<(u8, &'a [u8])> {
+        let [tag, first, rest @ ..] = self.data else {
+            return Err(invalid("数据意外结束"));
+        };
+        let (len, rest) = match *first {
+            len @ 0..0x80 => (len as usize, rest),
+            0x80 => return Err(invalid("不支持不定长编码")),
+            n => {
+                let n = (n & 0x7f) as usize;
+                if n > 4 || rest.len() < n {
+                    return Err(invalid("长度字段无效"));
+                }
+                let len = rest[..n]
+                    .iter()
+                    .fold(0usize, |acc, &b| acc << 8 | b as usize);
+                (len, &rest[n..])
+            }
+        };
+        if rest.len() < len {
+            return Err(invalid("长度超出数据末尾"));
+        }
+        let (content, rest) = rest.split_at(len);
+        self.data = rest;
+        Ok((*tag, content))
+    }
+
+    /// Read the next value, which must have tag `tag`.
+    pub fn read(&mut self, tag: u8) -> Result<&'a [u8]> {
+        match self.read_any()? {
+            (t, content) if t == tag => Ok(content),
+            (t, _) => Err(invalid(format!("应为标签 {:02x}，实际 {:02x}", tag, t))),
+        }
+    }
+
+    /// Read the next value only if it has tag `tag`.
+    pub fn read_optional(&mut self, tag: u8) -> Result<Option<&'a [u8]>> {
+        match self.peek_tag() {
+            Some(t) if t == tag => self.read(tag).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn sequence(&mut self) -> Result<Reader<'a>> {
+        self.read(SEQUENCE).map(Reader::new)
+    }
+
+    /// A non-negative `INTEGER` as big endian bytes without leading zeros.
+    pub fn uint(&mut self) -> Result<&'a [u8]> {
+        let content = self.read(INTEGER)?;
+        match content {
+            [] => Err(invalid("INTEGER 为空")),
+            [first, ..] if first & 0x80 != 0 => Err(invalid("INTEGER 为负数")),
+            _ => {
+                let zeros = content.iter().take_while(|&&b| b == 0).count();
+                Ok(&content[zeros..])
+            }
+        }
+    }
+
+    /// A non-negative `INTEGER` that fits in a `u64`.
+    pub fn small_uint(&mut self) -> Result<u64> {
+        let bytes = self.uint()?;
+        if bytes.len() > 8 {
+            return Err(invalid("INTEGER 过大"));
+        }
+        Ok(bytes.iter().fold(0, |acc, &b| acc << 8 | b as u64))
+    }
+
+    /// A `BIT STRING` without unused bits.
+    pub fn bit_string(&mut self) -> Result<&'a [u8]> {
+        match self.read(BIT_STRING)? {
+            [0, bits @ ..] => Ok(bits),
+            _ => Err(invalid("BIT STRING 含未用比特")),
+        }
+    }
+
+    /// Fail if anything is left after the expected values.
+    pub fn finish(self) -> Result<()> {
+        match self.data.is_empty() {
+            true => Ok(()),
+            false => Err(invalid("末尾有多余数据")),
+        }
+    }
+}
+
+/// Encode one value.
+pub fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|&&b| b == 0).count();
+        out.push(0x80 | (bytes.len() - skip) as u8);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+pub fn sequence(parts: &[&[u8]]) -> Vec<u8> {
+    tlv(SEQUENCE, &parts.concat())
+}
+
+/// A non-negative `INTEGER` from big endian bytes.
+pub fn uint(bytes: &[u8]) -> Vec<u8> {
+    let zeros = bytes.iter().take_while(|&&b| b == 0).count();
+    let bytes = &bytes[zeros..];
+    let mut content = Vec::with_capacity(bytes.len() + 1);
+    if bytes.first().is_none_or(|&b| b & 0x80 != 0) {
+        content.push(0);
+    }
+    content.extend_from_slice(bytes);
+    tlv(INTEGER, &content)
+}
+
+pub fn bit_string(bits: &[u8]) -> Vec<u8> {
+    tlv(BIT_STRING, &[&[0], bits].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_and_lengths() {
+        let long = vec![0xab; 300];
+        let encoded = sequence(&[&uint(&[0x00, 0x80]), &uint(&[]), &tlv(OCTET_STRING, &long)]);
+        assert_eq!(&encoded[..4], &[0x30, 0x82, 0x01, 0x37]);
+        assert_eq!(&encoded[4..11], &[0x02, 0x02, 0x00, 0x80, 0x02, 0x01, 0x00]);
+
+        let mut reader = Reader::top_sequence(&encoded).unwrap();
+        assert_eq!(reader.uint().unwrap(), &[0x80]);
+        assert_eq!(reader.small_uint().unwrap(), 0);
+        assert_eq!(reader.read_optional(NULL).unwrap(), None);
+        assert_eq!(reader.read(OCTET_STRING).unwrap(), &long[..]);
+        reader.finish().unwrap();
+
+        assert!(Reader::top_sequence(&encoded[..encoded.len() - 1]).is_err());
+        assert!(Reader::top_sequence(&[&encoded[..], &[0]].concat()).is_err());
+        assert!(Reader::new(&[0x02, 0x01, 0xff]).uint().is_err());
+        assert!(Reader::new(&[0x30, 0x80, 0x00, 0x00]).sequence().is_err());
+    }
+}
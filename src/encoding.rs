@@ -0,0 +1,168 @@
+//! 输入/输出编码：Hex、Base64、UTF-8 文本与文件
+
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+
+use crate::error::{GmError, Result};
+
+/// How the bytes of a text field are written.
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub enum Encoding {
+    /// Lower-case hex. Decoding accepts either case.
+    #[default]
+    Hex,
+    /// Upper-case hex. Decoding accepts either case.
+    HexUpper,
+    Base64,
+    /// URL-safe Base64 without padding. Decoding also accepts padded input.
+    Base64Url,
+    /// UTF-8 text taken verbatim.
+    Utf8,
+    /// The field holds a file path; the data is the file's raw bytes.
+    File,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 6] = [
+        Encoding::Hex,
+        Encoding::HexUpper,
+        Encoding::Base64,
+        Encoding::Base64Url,
+        Encoding::Utf8,
+        Encoding::File,
+    ];
+
+    /// Encodings that can represent an output value in a text field.
+    pub const TEXT: [Encoding; 5] = [
+        Encoding::Hex,
+        Encoding::HexUpper,
+        Encoding::Base64,
+        Encoding::Base64Url,
+        Encoding::Utf8,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Encoding::Hex => "Hex",
+            Encoding::HexUpper => "HEX (大写)",
+            Encoding::Base64 => "Base64",
+            Encoding::Base64Url => "Base64URL",
+            Encoding::Utf8 => "UTF-8 文本",
+            Encoding::File => "文件",
+        }
+    }
+
+    /// Short name used for the `name:value` syntax of the command line tool.
+    pub fn tag(self) -> &'static str {
+        match self {
+            Encoding::Hex => "hex",
+            Encoding::HexUpper => "HEX",
+            Encoding::Base64 => "b64",
+            Encoding::Base64Url => "b64url",
+            Encoding::Utf8 => "text",
+            Encoding::File => "file",
+        }
+    }
+
+    pub fn from_tag(tag: &str) -> Option<Encoding> {
+        Encoding::ALL.into_iter().find(|e| e.tag() == tag)
+    }
+
+    /// Split a `tag:value` string, defaulting to `default` when there is no known tag.
+    pub fn split_tagged(value: &str, default: Encoding) -> (Encoding, &str) {
+        if let Some((tag, rest)) = value.split_once(':')
+            && let Some(enc) = Encoding::from_tag(tag)
+        {
+            return (enc, rest);
+        }
+        (default, value)
+    }
+
+    /// Decode the contents of the text field `field`.
+    pub fn decode(self, field: &'static str, text: &str) -> Result<Vec<u8>> {
+        let invalid = || GmError::InvalidEncoding {
+            field,
+            encoding: self.label(),
+        };
+        match self {
+            Encoding::Hex | Encoding::HexUpper => {
+                let compact: String = text.split_whitespace().collect();
+                hex::decode(compact).map_err(|_| invalid())
+            }
+            Encoding::Base64 => {
+                let compact: String = text.split_whitespace().collect();
+                STANDARD.decode(compact).map_err(|_| invalid())
+            }
+            Encoding::Base64Url => {
+                let compact: String = text.split_whitespace().collect();
+                URL_SAFE_NO_PAD
+                    .decode(compact.trim_end_matches('='))
+                    .map_err(|_| invalid())
+            }
+            Encoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            Encoding::File => {
+                let path = text.trim();
+                std::fs::read(path).map_err(|e| GmError::Io {
+                    path: path.to_string(),
+                    detail: e.to_string(),
+                })
+            }
+        }
+    }
+
+    /// Encode `data` for display in a text field.
+    pub fn encode(self, data: &[u8]) -> Result<String> {
+        match self {
+            Encoding::Hex => Ok(hex::encode(data)),
+            Encoding::HexUpper => Ok(hex::encode_upper(data)),
+            Encoding::Base64 => Ok(STANDARD.encode(data)),
+            Encoding::Base64Url => Ok(URL_SAFE_NO_PAD.encode(data)),
+            Encoding::Utf8 => String::from_utf8(data.to_vec()).map_err(|_| GmError::NotUtf8),
+            Encoding::File => Err(GmError::InvalidEncoding {
+                field: "输出",
+                encoding: self.label(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_text_encodings() {
+        let data = [0x00u8, 0xfb, 0xff, 0x10, 0x7e];
+        for enc in Encoding::TEXT.into_iter().filter(|e| *e != Encoding::Utf8) {
+            let text = enc.encode(&data).unwrap();
+            assert_eq!(enc.decode("数据", &text).unwrap(), data, "{:?}", enc);
+        }
+        assert_eq!(Encoding::HexUpper.encode(&data).unwrap(), "00FBFF107E");
+        assert_eq!(Encoding::Base64Url.encode(&data).unwrap(), "APv_EH4");
+        assert_eq!(
+            Encoding::Base64Url.decode("数据", "APv_EH4=").unwrap(),
+            data
+        );
+        assert_eq!(Encoding::Hex.decode("数据", "00 fb\nFF107e").unwrap(), data);
+    }
+
+    #[test]
+    fn test_errors_and_tags() {
+        assert_eq!(
+            Encoding::Base64.decode("AAD", "***"),
+            Err(GmError::InvalidEncoding {
+                field: "AAD",
+                encoding: "Base64"
+            })
+        );
+        assert_eq!(Encoding::Utf8.encode(&[0xff]), Err(GmError::NotUtf8));
+        assert_eq!(
+            Encoding::split_tagged("b64:AQI=", Encoding::Hex),
+            (Encoding::Base64, "AQI=")
+        );
+        assert_eq!(
+            Encoding::split_tagged("0102", Encoding::Hex),
+            (Encoding::Hex, "0102")
+        );
+    }
+}
@@ -0,0 +1,162 @@
+//! 统一错误类型
+
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, GmError>;
+
+/// Failure of any `gm_tools` operation.
+///
+/// `Display` renders the Chinese message shown in the GUI / CLI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GmError {
+    /// A key does not have the length required by the algorithm.
+    InvalidKeyLength { expected: usize, actual: usize },
+    /// An IV / nonce does not have the length required by the algorithm.
+    InvalidIvLength { expected: usize, actual: usize },
+    /// A text field could not be decoded with the selected encoding.
+    InvalidEncoding {
+        field: &'static str,
+        encoding: &'static str,
+    },
+    /// Output bytes are not valid UTF-8 and cannot be shown as text.
+    NotUtf8,
+    /// Reading or writing a file failed.
+    Io { path: String, detail: String },
+    /// Reading or writing a data stream failed part way through.
+    Stream(String),
+    /// A long-running operation was cancelled by the user.
+    Cancelled,
+    /// A required input is empty.
+    EmptyInput { field: &'static str },
+    /// An input is longer than the operation accepts.
+    InputTooLong { max: usize, actual: usize },
+    /// A requested derived key length exceeds what the KDF can produce.
+    InvalidOutputLength { max: usize, actual: usize },
+    /// A PBKDF2 iteration count exceeds [`crate::kdf::PBKDF2_MAX_ITERATIONS`].
+    TooManyIterations { max: u32, actual: u32 },
+    /// Unpadded data is not a multiple of the block size.
+    InvalidDataLength { block_size: usize, actual: usize },
+    /// PKCS#7 padding is malformed.
+    PaddingInvalid,
+    /// GCM authentication tag does not match.
+    TagMismatch,
+    /// A truncated MAC tag length is outside the accepted range.
+    InvalidTagLength {
+        actual: usize,
+        min: usize,
+        max: usize,
+    },
+    /// The MAC message bit length exceeds the supplied data.
+    MessageBitsOutOfRange { bits: usize, available: usize },
+    /// The private key is not a valid hex integer.
+    InvalidPrivateKey,
+    /// The private key is not in `[1, n - 2]`.
+    PrivateKeyOutOfRange,
+    /// The public key could not be parsed.
+    InvalidPublicKey(String),
+    /// A fixed SM2 `k` is out of range, gives `r = 0` / `s = 0` when signing
+    /// or an all-zero KDF output when encrypting.
+    InvalidK,
+    /// The signature could not be decoded.
+    InvalidSignatureEncoding(String),
+    /// A ciphertext is shorter than its fixed overhead (GCM tag, SM2 C1 || C3).
+    CiphertextTooShort { len: usize, min: usize },
+    /// An SM2 ciphertext does not match the expected layout.
+    InvalidCiphertext(String),
+    /// The C3 hash of a decrypted SM2 ciphertext does not match.
+    CiphertextHashMismatch,
+    /// An SM2 key exchange input is out of range or a shared point is degenerate.
+    KeyExchange(String),
+    /// Sealed settings or an encrypted key file could not be decrypted: wrong
+    /// passphrase or corrupted data.
+    WrongPassphrase,
+    /// Sealed settings are not in the expected format.
+    InvalidSealedData(String),
+    /// A checksum manifest line is not `<hex digest>  <path>`.
+    InvalidManifest { line: usize, detail: String },
+    /// Malformed DER data.
+    InvalidDer(String),
+    /// A key file is well-formed DER but not a supported SM2 key.
+    InvalidKeyFile(String),
+    /// An operation inside `libsm` failed.
+    Libsm { op: &'static str, detail: String },
+}
+
+impl fmt::Display for GmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GmError::InvalidKeyLength { expected, actual } => write!(
+                f,
+                "Key 必须是 {} 字节 ({} hex characters)，实际 {} 字节",
+                expected,
+                expected * 2,
+                actual
+            ),
+            GmError::InvalidIvLength { expected, actual } => write!(
+                f,
+                "IV 必须是 {} 字节 ({} hex characters)，实际 {} 字节",
+                expected,
+                expected * 2,
+                actual
+            ),
+            GmError::InvalidEncoding { field, encoding } => {
+                write!(f, "{} 不是合法的 {} 编码", field, encoding)
+            }
+            GmError::NotUtf8 => write!(f, "结果不是合法的 UTF-8 文本，请选择 Hex 或 Base64 输出"),
+            GmError::Io { path, detail } => write!(f, "文件 {} 读写失败: {}", path, detail),
+            GmError::Stream(detail) => write!(f, "数据读写失败: {}", detail),
+            GmError::Cancelled => write!(f, "操作已取消"),
+            GmError::EmptyInput { field } => write!(f, "{} 不能为空", field),
+            GmError::InputTooLong { max, actual } => {
+                write!(f, "输入过长: 最多 {} 字节 (实际 {} 字节)", max, actual)
+            }
+            GmError::InvalidOutputLength { max, actual } => {
+                write!(f, "输出长度最多 {} 字节 (实际 {} 字节)", max, actual)
+            }
+            GmError::TooManyIterations { max, actual } => {
+                write!(f, "迭代次数最多 {} 次 (实际 {} 次)", max, actual)
+            }
+            GmError::InvalidDataLength { block_size, actual } => write!(
+                f,
+                "数据长度必须是 {} 的倍数 (实际 {} 字节)",
+                block_size, actual
+            ),
+            GmError::PaddingInvalid => write!(f, "Padding 校验失败 (PKCS7)"),
+            GmError::TagMismatch => write!(f, "解密失败: 认证标签 (Tag) 不匹配"),
+            GmError::InvalidTagLength { actual, min, max } => write!(
+                f,
+                "MAC 长度必须在 {} 到 {} 字节之间 (实际 {} 字节)",
+                min, max, actual
+            ),
+            GmError::MessageBitsOutOfRange { bits, available } => {
+                write!(f, "消息比特长度 {} 超出数据长度 {} bits", bits, available)
+            }
+            GmError::InvalidPrivateKey => write!(f, "Private key 格式错误"),
+            GmError::PrivateKeyOutOfRange => write!(f, "私钥超出范围: 须满足 1 ≤ d ≤ n-2"),
+            GmError::InvalidPublicKey(detail) => write!(f, "公钥解析失败: {}", detail),
+            GmError::InvalidK => write!(
+                f,
+                "随机数 k 无效: 须满足 1 ≤ k < n，且签名时 r、s 不为 0、加密时 KDF 输出不全为 0"
+            ),
+            GmError::InvalidSignatureEncoding(detail) => write!(f, "签名解析失败: {}", detail),
+            GmError::CiphertextTooShort { len, min } => {
+                write!(f, "密文长度不足 ({} 字节)，至少需要 {} 字节", len, min)
+            }
+            GmError::InvalidCiphertext(detail) => write!(f, "密文格式错误: {}", detail),
+            GmError::CiphertextHashMismatch => {
+                write!(f, "C3 校验失败: 私钥不匹配、密文格式不符或密文已损坏")
+            }
+            GmError::KeyExchange(detail) => write!(f, "密钥交换失败: {}", detail),
+            GmError::WrongPassphrase => write!(f, "口令错误，或加密保存的数据已损坏"),
+            GmError::InvalidSealedData(detail) => write!(f, "加密保存的数据格式错误: {}", detail),
+            GmError::InvalidManifest { line, detail } => {
+                write!(f, "校验清单第 {} 行格式错误: {}", line, detail)
+            }
+            GmError::InvalidDer(detail) => write!(f, "DER 解析失败: {}", detail),
+            GmError::InvalidKeyFile(detail) => write!(f, "密钥文件格式错误: {}", detail),
+            GmError::Libsm { op, detail } => write!(f, "{}失败: {}", op, detail),
+        }
+    }
+}
+
+impl std::error::Error for GmError {}
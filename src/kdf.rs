@@ -0,0 +1,217 @@
+//! 密钥派生：SM2 KDF (GB/T 32918.4)、PBKDF2-HMAC-SM3 与 HKDF-SM3
+
+use sm3::{Digest, Sm3};
+
+use crate::error::{GmError, Result};
+use crate::sm3::{DIGEST_LEN, HmacKey};
+
+/// Longest HKDF-SM3 output: 255 blocks of one digest (RFC 5869).
+pub const HKDF_MAX_LEN: usize = 255 * DIGEST_LEN;
+
+/// Longest output of [`sm2_kdf`] and [`pbkdf2_hmac_sm3`], the same as for HKDF.
+pub const MAX_OUTPUT_LEN: usize = HKDF_MAX_LEN;
+
+fn check_output_len(out_len: usize) -> Result<()> {
+    if out_len > MAX_OUTPUT_LEN {
+        return Err(GmError::InvalidOutputLength {
+            max: MAX_OUTPUT_LEN,
+            actual: out_len,
+        });
+    }
+    Ok(())
+}
+
+/// The SM2 key derivation function: `SM3(Z || ct)` for `ct = 1, 2, ..`
+/// (32-bit big endian), concatenated and cut to `out_len` bytes
+/// (at most [`MAX_OUTPUT_LEN`]).
+pub fn sm2_kdf(z: &[u8], out_len: usize) -> Result<Vec<u8>> {
+    check_output_len(out_len)?;
+    Ok(key_stream(z, out_len))
+}
+
+/// [`sm2_kdf`] without the length limit, for SM2 encryption, whose mask is
+/// as long as the message already held in memory.
+pub(crate) fn key_stream(z: &[u8], out_len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(out_len);
+    let mut ct = 1u32;
+    while out.len() < out_len {
+        let mut hasher = Sm3::new();
+        hasher.update(z);
+        hasher.update(ct.to_be_bytes());
+        let block: [u8; DIGEST_LEN] = hasher.finalize().into();
+        let take = (out_len - out.len()).min(DIGEST_LEN);
+        out.extend_from_slice(&block[..take]);
+        ct += 1;
+    }
+    out
+}
+
+/// Most iterations [`pbkdf2_hmac_sm3`] accepts: a count taken from user input
+/// or from a file could otherwise stall the program for hours.
+pub const PBKDF2_MAX_ITERATIONS: u32 = 10_000_000;
+
+/// PBKDF2 (RFC 8018) with HMAC-SM3 as the PRF, producing `out_len` bytes
+/// (at most [`MAX_OUTPUT_LEN`]) with at most [`PBKDF2_MAX_ITERATIONS`].
+///
+/// An `iterations` of 0 is treated as 1.
+pub fn pbkdf2_hmac_sm3(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    out_len: usize,
+) -> Result<Vec<u8>> {
+    check_output_len(out_len)?;
+    if iterations > PBKDF2_MAX_ITERATIONS {
+        return Err(GmError::TooManyIterations {
+            max: PBKDF2_MAX_ITERATIONS,
+            actual: iterations,
+        });
+    }
+    let prf = HmacKey::new(password);
+    let mut out = Vec::with_capacity(out_len);
+    let mut block_index = 1u32;
+    while out.len() < out_len {
+        let mut first = salt.to_vec();
+        first.extend_from_slice(&block_index.to_be_bytes());
+        let mut u = prf.mac(&first);
+        let mut t = u;
+        for _ in 1..iterations {
+            u = prf.mac(&u);
+            for (t, u) in t.iter_mut().zip(&u) {
+                *t ^= u;
+            }
+        }
+        let take = (out_len - out.len()).min(DIGEST_LEN);
+        out.extend_from_slice(&t[..take]);
+        block_index += 1;
+    }
+    Ok(out)
+}
+
+/// HKDF-Extract (RFC 5869) with HMAC-SM3: `PRK = HMAC(salt, IKM)`.
+///
+/// An empty salt acts as 32 zero bytes, as the RFC specifies.
+pub fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> [u8; DIGEST_LEN] {
+    HmacKey::new(salt).mac(ikm)
+}
+
+/// HKDF-Expand (RFC 5869) with HMAC-SM3, producing `out_len` bytes
+/// (at most [`HKDF_MAX_LEN`]).
+pub fn hkdf_expand(prk: &[u8], info: &[u8], out_len: usize) -> Result<Vec<u8>> {
+    if out_len > HKDF_MAX_LEN {
+        return Err(GmError::InvalidOutputLength {
+            max: HKDF_MAX_LEN,
+            actual: out_len,
+        });
+    }
+    let prf = HmacKey::new(prk);
+    let mut out = Vec::with_capacity(out_len);
+    let mut t = Vec::new();
+    for counter in 1..=u8::MAX {
+        if out.len() >= out_len {
+            break;
+        }
+        t.extend_from_slice(info);
+        t.push(counter);
+        let block = prf.mac(&t);
+        let take = (out_len - out.len()).min(DIGEST_LEN);
+        out.extend_from_slice(&block[..take]);
+        t = block.to_vec();
+    }
+    Ok(out)
+}
+
+/// HKDF-Extract followed by HKDF-Expand.
+pub fn hkdf(salt: &[u8], ikm: &[u8], info: &[u8], out_len: usize) -> Result<Vec<u8>> {
+    hkdf_expand(&hkdf_extract(salt, ikm), info, out_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pbkdf2_hmac_sm3() {
+        assert_eq!(
+            hex::encode(pbkdf2_hmac_sm3(b"password", b"salt", 2, 32).unwrap()),
+            "fee723a2bc966e11dffb66133f4e8df577383c78ade30e3298edbd3e54ed85b7"
+        );
+        // Output longer than one digest spans several blocks.
+        let long = pbkdf2_hmac_sm3(b"password", b"salt", 2, 40).unwrap();
+        assert_eq!(long.len(), 40);
+        assert_eq!(hex::encode(&long[32..]), "650006f9e15d3798");
+    }
+
+    #[test]
+    fn test_sm2_kdf() {
+        // GB/T 32918.5-2017 encryption example: t = KDF(x2 || y2, klen) with C2 = M xor t
+        let z = hex::decode(
+            "335e18d751e51f040e27d468138b7ab1dc86ad7f981d7d416222fd6ab3ed230d\
+             ab743ebcfb22d64f7b6ab791f70658f25b48fa93e54064fdbfbed3f0bd847ac9",
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(sm2_kdf(&z, 19).unwrap()),
+            "44e60fdbf0bae81437665374bef26749046c9e"
+        );
+        assert_eq!(
+            hex::encode(&sm2_kdf(&z, 40).unwrap()[32..]),
+            "75abe2630d06376d"
+        );
+    }
+
+    #[test]
+    fn test_output_length_limit() {
+        let too_long = Err(GmError::InvalidOutputLength {
+            max: MAX_OUTPUT_LEN,
+            actual: MAX_OUTPUT_LEN + 1,
+        });
+        assert_eq!(sm2_kdf(b"z", MAX_OUTPUT_LEN).unwrap().len(), MAX_OUTPUT_LEN);
+        assert_eq!(sm2_kdf(b"z", MAX_OUTPUT_LEN + 1), too_long);
+        assert_eq!(pbkdf2_hmac_sm3(b"p", b"s", 1, MAX_OUTPUT_LEN + 1), too_long);
+        assert_eq!(
+            pbkdf2_hmac_sm3(b"p", b"s", u32::MAX, 16),
+            Err(GmError::TooManyIterations {
+                max: PBKDF2_MAX_ITERATIONS,
+                actual: u32::MAX
+            })
+        );
+        assert_eq!(
+            key_stream(b"z", MAX_OUTPUT_LEN + 1).len(),
+            MAX_OUTPUT_LEN + 1
+        );
+    }
+
+    #[test]
+    fn test_hkdf_sm3() {
+        // Inputs of RFC 5869 test case 1, with SM3 in place of SHA-256
+        let ikm = [0x0b; 22];
+        let salt: Vec<u8> = (0..13).collect();
+        let info: Vec<u8> = (0xf0..0xfa).collect();
+        assert_eq!(
+            hex::encode(hkdf_extract(&salt, &ikm)),
+            "e0d6f7b0bd056327b7659f1f39ad850561fbcf4fb10fb58e88eafa55cf7cd01e"
+        );
+        assert_eq!(
+            hex::encode(hkdf(&salt, &ikm, &info, 42).unwrap()),
+            "c69fe91b7aaee2dd5718d72dcaee0cce93f1b8e41f792da51261b6a517e68b36\
+             ed2c595572b01dfa359b"
+        );
+        assert_eq!(
+            hex::encode(hkdf(&[], &ikm, &[], 42).unwrap()),
+            "c8c91a38ae2fb3b023a7c38ce9f0748f28230d59b6b950ba3ba949bf0d713a57\
+             74815778801741cb2034"
+        );
+        assert_eq!(
+            hkdf_expand(&[0; 32], &[], HKDF_MAX_LEN).unwrap().len(),
+            HKDF_MAX_LEN
+        );
+        assert_eq!(
+            hkdf_expand(&[0; 32], &[], HKDF_MAX_LEN + 1),
+            Err(GmError::InvalidOutputLength {
+                max: HKDF_MAX_LEN,
+                actual: HKDF_MAX_LEN + 1
+            })
+        );
+    }
+}
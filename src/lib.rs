@@ -0,0 +1,25 @@
+//! 国密算法工具箱 (GM Tools) 核心库
+//!
+//! GUI 与其他二进制共用的 SM2/SM3/SM4/ZUC 算法实现，
+//! 所有函数只处理原始字节，不涉及任何界面状态。
+
+pub mod checksum;
+mod der;
+pub mod encoding;
+pub mod error;
+pub mod kdf;
+pub mod selftest;
+pub mod settings;
+pub mod sm2;
+pub mod sm2_exchange;
+pub mod sm2_keycheck;
+pub mod sm2_keyfile;
+pub mod sm3;
+pub mod sm3_trace;
+pub mod sm4;
+pub mod stream;
+pub mod zuc;
+pub mod zuc256_new;
+
+pub use encoding::Encoding;
+pub use error::{GmError, Result};
@@ -0,0 +1,219 @@
+//! 会话设置：`key = value` 文本文件，以及用口令加密保存的密钥材料
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::{GmError, Result};
+use crate::{kdf, sm4, stream};
+
+/// PBKDF2 iterations used by [`Settings::seal`].
+pub const SEAL_ITERATIONS: u32 = 100_000;
+const SEAL_SCHEME: &str = "pbkdf2-sm3-sm4gcm";
+const SEAL_AAD: &[u8] = b"gm_tools settings";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Flat string settings, stored as one `key = value` per line.
+///
+/// Line breaks and backslashes in values are escaped; lines starting with `#`
+/// and lines without `=` are ignored.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct Settings {
+    entries: BTreeMap<String, String>,
+}
+
+impl Settings {
+    /// `gm_tools/settings.conf` under the platform config directory:
+    /// `%APPDATA%` on Windows, `$XDG_CONFIG_HOME` or `~/.config` elsewhere.
+    pub fn default_path() -> Option<PathBuf> {
+        let var = |name| {
+            std::env::var_os(name)
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from)
+        };
+        let base = if cfg!(windows) {
+            var("APPDATA")?
+        } else {
+            var("XDG_CONFIG_HOME").or_else(|| var("HOME").map(|home| home.join(".config")))?
+        };
+        Some(base.join("gm_tools").join("settings.conf"))
+    }
+
+    /// Read settings from `path`. A missing file gives empty settings.
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(stream::io_error(path, e)),
+        }
+    }
+
+    /// Write settings to `path`, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| stream::io_error(dir, e))?;
+        }
+        std::fs::write(path, self.to_text()).map_err(|e| stream::io_error(path, e))
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let entries = text
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| {
+                let value = value.strip_prefix(' ').unwrap_or(value);
+                (key.trim().to_string(), unescape(value))
+            })
+            .collect();
+        Self { entries }
+    }
+
+    pub fn to_text(&self) -> String {
+        self.entries
+            .iter()
+            .map(|(key, value)| format!("{} = {}\n", key, escape(value)))
+            .collect()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(String::as_str)
+    }
+
+    pub fn set(&mut self, key: &str, value: impl Into<String>) {
+        self.entries.insert(key.to_string(), value.into());
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.entries.remove(key)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Encrypt these settings under `passphrase` into a single-line value:
+    /// SM4-GCM with a key derived by PBKDF2-HMAC-SM3 from a random salt.
+    pub fn seal(&self, passphrase: &str) -> Result<String> {
+        if passphrase.is_empty() {
+            return Err(GmError::EmptyInput { field: "口令" });
+        }
+        let salt: [u8; SALT_LEN] = rand::random();
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let key = seal_key(passphrase, &salt, SEAL_ITERATIONS)?;
+        let ciphertext = sm4::gcm_encrypt(&key, &nonce, SEAL_AAD, self.to_text().as_bytes())?;
+        Ok(format!(
+            "{}${}${}${}${}",
+            SEAL_SCHEME,
+            SEAL_ITERATIONS,
+            hex::encode(salt),
+            hex::encode(nonce),
+            hex::encode(ciphertext)
+        ))
+    }
+
+    /// Decrypt a value produced by [`Settings::seal`].
+    pub fn unseal(sealed: &str, passphrase: &str) -> Result<Self> {
+        let invalid = |detail: &str| GmError::InvalidSealedData(detail.to_string());
+        let parts: Vec<&str> = sealed.trim().split('$').collect();
+        let [scheme, iterations, salt, nonce, ciphertext] = parts[..] else {
+            return Err(invalid("字段数量不正确"));
+        };
+        if scheme != SEAL_SCHEME {
+            return Err(invalid(&format!("未知的加密方案 {}", scheme)));
+        }
+        let iterations: u32 = iterations.parse().map_err(|_| invalid("迭代次数"))?;
+        let salt = hex::decode(salt).map_err(|_| invalid("salt"))?;
+        let nonce = hex::decode(nonce).map_err(|_| invalid("nonce"))?;
+        let ciphertext = hex::decode(ciphertext).map_err(|_| invalid("密文"))?;
+
+        let key = seal_key(passphrase, &salt, iterations)?;
+        let plaintext = match sm4::gcm_decrypt(&key, &nonce, SEAL_AAD, &ciphertext) {
+            Ok(p) => p,
+            Err(GmError::TagMismatch) => return Err(GmError::WrongPassphrase),
+            Err(e) => return Err(e),
+        };
+        let text = String::from_utf8(plaintext).map_err(|_| invalid("内容不是 UTF-8 文本"))?;
+        Ok(Self::parse(&text))
+    }
+}
+
+fn seal_key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<Vec<u8>> {
+    kdf::pbkdf2_hmac_sm3(passphrase.as_bytes(), salt, iterations, sm4::KEY_LEN)
+}
+
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_roundtrip() {
+        let mut settings = Settings::default();
+        settings.set("sm4.mode", "gcm");
+        settings.set("sm4.aad", " two\nlines \\ ");
+        let text = settings.to_text();
+        assert_eq!(text, "sm4.aad =  two\\nlines \\\\ \nsm4.mode = gcm\n");
+        assert_eq!(Settings::parse(&text), settings);
+        assert_eq!(
+            Settings::parse("# comment\nnoise\n tab=SM2").get("tab"),
+            Some("SM2")
+        );
+    }
+
+    #[test]
+    fn test_seal_roundtrip_and_wrong_passphrase() {
+        let mut secrets = Settings::default();
+        secrets.set("sm4.key", "0123456789abcdeffedcba9876543210");
+        let sealed = secrets.seal("correct horse").unwrap();
+        assert!(sealed.starts_with("pbkdf2-sm3-sm4gcm$100000$"));
+        assert_eq!(Settings::unseal(&sealed, "correct horse").unwrap(), secrets);
+        assert_eq!(
+            Settings::unseal(&sealed, "wrong"),
+            Err(GmError::WrongPassphrase)
+        );
+        assert!(matches!(
+            Settings::unseal("garbage", "correct horse"),
+            Err(GmError::InvalidSealedData(_))
+        ));
+        let tampered = sealed.replacen("$100000$", "$4294967295$", 1);
+        assert_eq!(
+            Settings::unseal(&tampered, "correct horse"),
+            Err(GmError::TooManyIterations {
+                max: kdf::PBKDF2_MAX_ITERATIONS,
+                actual: u32::MAX
+            })
+        );
+        assert_eq!(secrets.seal(""), Err(GmError::EmptyInput { field: "口令" }));
+    }
+}
@@ -0,0 +1,949 @@
+//! SM2 椭圆曲线公钥密码 (GB/T 32918)，基于 `libsm`
+
+use libsm::sm2::ecc::EccCtx;
+use num_bigint::BigUint;
+use num_traits::{Num, One, Zero};
+
+use crate::der::{self, Reader};
+use crate::error::{GmError, Result};
+use crate::{kdf, sm3};
+
+pub use libsm::sm2::ecc::Point;
+pub use libsm::sm2::signature::{SigCtx, Signature};
+
+/// User ID libsm uses for ZA when none is given.
+pub const DEFAULT_USER_ID: &str = "1234567812345678";
+/// Longest user ID whose bit length fits the 16-bit ENTL field of ZA.
+pub const MAX_USER_ID_LEN: usize = 0xffff / 8;
+
+/// Field prime p of the recommended curve.
+pub(crate) const FIELD_P: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+];
+/// Length of C1 (uncompressed point) in an SM2 ciphertext.
+const C1_LEN: usize = 65;
+/// Length of the coordinates x || y of C1.
+const C1_XY_LEN: usize = 64;
+/// Length of C3 (SM3 digest) in an SM2 ciphertext.
+const C3_LEN: usize = 32;
+
+/// Length of a raw `r || s` signature.
+pub const RAW_SIGNATURE_LEN: usize = 64;
+
+/// How a signature `(r, s)` is serialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SignatureFormat {
+    /// `SEQUENCE { INTEGER r, INTEGER s }`.
+    #[default]
+    Der,
+    /// 32-byte big endian `r` followed by `s`, as in GM/T 0009 style APIs.
+    Raw,
+}
+
+impl SignatureFormat {
+    pub const ALL: [SignatureFormat; 2] = [SignatureFormat::Der, SignatureFormat::Raw];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SignatureFormat::Der => "DER",
+            SignatureFormat::Raw => "r||s (64 字节)",
+        }
+    }
+
+    /// Name used in saved settings and on the command line.
+    pub fn tag(self) -> &'static str {
+        match self {
+            SignatureFormat::Der => "der",
+            SignatureFormat::Raw => "raw",
+        }
+    }
+
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.tag() == tag)
+    }
+}
+
+/// Layout of the parts C1 (point `kG`), C3 (SM3 hash) and C2 (masked
+/// message) of a ciphertext.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CiphertextFormat {
+    /// `04 || x || y || C3 || C2`, as in GB/T 32918.4-2016.
+    C1C3C2,
+    /// `04 || x || y || C2 || C3`, libsm's layout.
+    #[default]
+    C1C2C3,
+    /// C1C3C2 without the `04` prefix of C1.
+    C1C3C2Bare,
+    /// C1C2C3 without the `04` prefix of C1.
+    C1C2C3Bare,
+    /// GM/T 0009 `SM2Cipher ::= SEQUENCE { x INTEGER, y INTEGER,
+    /// hash OCTET STRING, cipherText OCTET STRING }`.
+    Asn1,
+}
+
+impl CiphertextFormat {
+    pub const ALL: [CiphertextFormat; 5] = [
+        CiphertextFormat::C1C3C2,
+        CiphertextFormat::C1C2C3,
+        CiphertextFormat::C1C3C2Bare,
+        CiphertextFormat::C1C2C3Bare,
+        CiphertextFormat::Asn1,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CiphertextFormat::C1C3C2 => "C1C3C2",
+            CiphertextFormat::C1C2C3 => "C1C2C3",
+            CiphertextFormat::C1C3C2Bare => "C1C3C2 (C1 无 04)",
+            CiphertextFormat::C1C2C3Bare => "C1C2C3 (C1 无 04)",
+            CiphertextFormat::Asn1 => "ASN.1 DER",
+        }
+    }
+
+    /// Name used in saved settings and on the command line.
+    pub fn tag(self) -> &'static str {
+        match self {
+            CiphertextFormat::C1C3C2 => "c1c3c2",
+            CiphertextFormat::C1C2C3 => "c1c2c3",
+            CiphertextFormat::C1C3C2Bare => "c1c3c2-bare",
+            CiphertextFormat::C1C2C3Bare => "c1c2c3-bare",
+            CiphertextFormat::Asn1 => "asn1",
+        }
+    }
+
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.tag() == tag)
+    }
+
+    /// Length of C1 in the raw layouts; `None` for ASN.1.
+    fn c1_len(self) -> Option<usize> {
+        match self {
+            CiphertextFormat::C1C3C2 | CiphertextFormat::C1C2C3 => Some(C1_LEN),
+            CiphertextFormat::C1C3C2Bare | CiphertextFormat::C1C2C3Bare => Some(C1_XY_LEN),
+            CiphertextFormat::Asn1 => None,
+        }
+    }
+
+    fn c3_first(self) -> bool {
+        matches!(
+            self,
+            CiphertextFormat::C1C3C2 | CiphertextFormat::C1C3C2Bare
+        )
+    }
+}
+
+/// The parts of a ciphertext, whatever its layout.
+struct CiphertextParts {
+    /// `x || y` of C1.
+    c1: Vec<u8>,
+    c3: Vec<u8>,
+    c2: Vec<u8>,
+}
+
+impl CiphertextParts {
+    /// Split `data` laid out as `format`, checking that C1 is on the curve.
+    fn parse(data: &[u8], format: CiphertextFormat) -> Result<Self> {
+        let invalid = |detail: &str| GmError::InvalidCiphertext(detail.into());
+        let parts = match format.c1_len() {
+            Some(c1_len) => {
+                let min = c1_len + C3_LEN;
+                if data.len() < min {
+                    return Err(GmError::CiphertextTooShort {
+                        len: data.len(),
+                        min,
+                    });
+                }
+                let (c1, rest) = data.split_at(c1_len);
+                let c1 = match c1 {
+                    [0x04, xy @ ..] if c1_len == C1_LEN => xy,
+                    [prefix, ..] if c1_len == C1_LEN => {
+                        return Err(invalid(&format!("C1 应以 04 开头，实际为 {:02x}", prefix)));
+                    }
+                    xy => xy,
+                };
+                let (c3, c2) = match format.c3_first() {
+                    true => rest.split_at(C3_LEN),
+                    false => {
+                        let (c2, c3) = rest.split_at(rest.len() - C3_LEN);
+                        (c3, c2)
+                    }
+                };
+                CiphertextParts {
+                    c1: c1.to_vec(),
+                    c3: c3.to_vec(),
+                    c2: c2.to_vec(),
+                }
+            }
+            None => {
+                let mut reader = Reader::top_sequence(data)?;
+                let mut c1 = Vec::with_capacity(C1_XY_LEN);
+                for _ in 0..2 {
+                    let coordinate = reader.uint()?;
+                    if coordinate.len() > 32 {
+                        return Err(invalid("C1 坐标超过 32 字节"));
+                    }
+                    c1.extend(std::iter::repeat_n(0, 32 - coordinate.len()));
+                    c1.extend_from_slice(coordinate);
+                }
+                let c3 = reader.read(der::OCTET_STRING)?;
+                let c2 = reader.read(der::OCTET_STRING)?;
+                reader.finish()?;
+                if c3.len() != C3_LEN {
+                    return Err(invalid(&format!(
+                        "C3 应为 {} 字节，实际 {} 字节",
+                        C3_LEN,
+                        c3.len()
+                    )));
+                }
+                CiphertextParts {
+                    c1,
+                    c3: c3.to_vec(),
+                    c2: c2.to_vec(),
+                }
+            }
+        };
+        parts.c1_point()?;
+        Ok(parts)
+    }
+
+    fn c1_point(&self) -> Result<Point> {
+        load_public_key(&SigCtx::new(), &self.c1).map_err(|e| match e {
+            GmError::InvalidPublicKey(detail) => {
+                GmError::InvalidCiphertext(format!("C1 {}", detail))
+            }
+            other => other,
+        })
+    }
+
+    fn to_bytes(&self, format: CiphertextFormat) -> Vec<u8> {
+        let c1: &[u8] = &self.c1;
+        let prefixed = [&[0x04], c1].concat();
+        let c1 = match format.c1_len() {
+            Some(C1_LEN) => &prefixed[..],
+            Some(_) => c1,
+            None => {
+                return der::sequence(&[
+                    &der::uint(&self.c1[..32]),
+                    &der::uint(&self.c1[32..]),
+                    &der::tlv(der::OCTET_STRING, &self.c3),
+                    &der::tlv(der::OCTET_STRING, &self.c2),
+                ]);
+            }
+        };
+        match format.c3_first() {
+            true => [c1, &self.c3, &self.c2].concat(),
+            false => [c1, &self.c2, &self.c3].concat(),
+        }
+    }
+}
+
+/// Re-arrange a ciphertext from one layout to another. The layout cannot be
+/// detected without the private key, so `from` must be given.
+pub fn convert_ciphertext(
+    data: &[u8],
+    from: CiphertextFormat,
+    to: CiphertextFormat,
+) -> Result<Vec<u8>> {
+    CiphertextParts::parse(data, from).map(|parts| parts.to_bytes(to))
+}
+
+/// Result of [`decrypt`].
+pub struct DecryptOutput {
+    pub plaintext: Vec<u8>,
+    /// Layout of the input: the one asked for, or the one that decrypted.
+    pub format: CiphertextFormat,
+}
+
+pub fn generate_keypair(ctx: &SigCtx) -> Result<(Point, BigUint)> {
+    ctx.new_keypair().map_err(|e| libsm_error("密钥生成", e))
+}
+
+pub fn private_key_to_hex(sk: &BigUint) -> String {
+    format!("{:0>64}", sk.to_str_radix(16))
+}
+
+/// Encode a public key as uncompressed `04||X||Y` hex.
+pub fn public_key_to_hex(ctx: &SigCtx, pk: &Point) -> Result<String> {
+    public_key_to_bytes(ctx, pk).map(hex::encode)
+}
+
+/// Encode a public key as uncompressed `04||X||Y`.
+pub fn public_key_to_bytes(ctx: &SigCtx, pk: &Point) -> Result<Vec<u8>> {
+    ctx.serialize_pubkey(pk, false)
+        .map_err(|e| libsm_error("公钥编码", e))
+}
+
+/// Parse a hex public key: uncompressed `04||X||Y`, compressed `02/03||X`
+/// or bare `X||Y`. Whitespace is ignored.
+///
+/// The point must lie on the curve with coordinates below p; the point at
+/// infinity is rejected.
+pub fn parse_public_key(ctx: &SigCtx, hex_str: &str) -> Result<Point> {
+    let hex_str: String = hex_str.split_whitespace().collect();
+    if hex_str.is_empty() {
+        return Err(GmError::EmptyInput { field: "公钥" });
+    }
+    let bytes =
+        hex::decode(&hex_str).map_err(|_| GmError::InvalidPublicKey("不是合法的 Hex".into()))?;
+    load_public_key(ctx, &bytes)
+}
+
+/// [`parse_public_key`] on raw bytes.
+pub fn load_public_key(ctx: &SigCtx, bytes: &[u8]) -> Result<Point> {
+    let invalid = |detail: &str| GmError::InvalidPublicKey(detail.into());
+    let encoded = match (bytes.len(), bytes.first()) {
+        (65, Some(0x04)) | (33, Some(0x02 | 0x03)) => bytes.to_vec(),
+        (64, _) => [&[0x04], bytes].concat(),
+        (65 | 33, Some(prefix)) => {
+            return Err(invalid(&format!("未知的点编码前缀 {:02x}", prefix)));
+        }
+        (len, _) => {
+            return Err(invalid(&format!(
+                "长度应为 65 (04||X||Y)、33 (02/03||X) 或 64 (X||Y) 字节，实际 {} 字节",
+                len
+            )));
+        }
+    };
+    let p = BigUint::from_bytes_be(&FIELD_P);
+    if encoded[1..]
+        .chunks(32)
+        .any(|c| BigUint::from_bytes_be(c) >= p)
+    {
+        return Err(invalid("坐标不小于素数 p"));
+    }
+    // With the length, prefix and range checked, libsm only fails when
+    // y² ≠ x³ + ax + b, or no such y exists for a compressed x
+    let point = ctx
+        .load_pubkey(&encoded)
+        .map_err(|_| invalid("点不在 SM2 曲线上"))?;
+    if point.is_zero() {
+        return Err(invalid("不能是无穷远点"));
+    }
+    Ok(point)
+}
+
+/// Parse a hex private key `d`, which must satisfy 1 ≤ d ≤ n - 2.
+pub fn parse_private_key(hex_str: &str) -> Result<BigUint> {
+    let hex_str: String = hex_str.split_whitespace().collect();
+    let sk = BigUint::from_str_radix(&hex_str, 16).map_err(|_| GmError::InvalidPrivateKey)?;
+    check_private_key(&sk)?;
+    Ok(sk)
+}
+
+/// Check 1 ≤ d ≤ n - 2.
+pub fn check_private_key(sk: &BigUint) -> Result<()> {
+    // (1 + d) must be invertible mod n when signing, so d = n - 1 is excluded too
+    if sk.is_zero() || sk + 2u32 > *EccCtx::new().get_n() {
+        return Err(GmError::PrivateKeyOutOfRange);
+    }
+    Ok(())
+}
+
+/// The public key `P = d·G` of private key `sk`.
+pub fn public_key_from_private(sk: &BigUint) -> Result<Point> {
+    EccCtx::new()
+        .g_mul(sk)
+        .map_err(|e| libsm_error("公钥计算", e))
+}
+
+/// `ZA = SM3(ENTL || ID || a || b || xG || yG || xA || yA)`, the hash of the
+/// signer's identity that prefixes every signed message.
+pub fn za(pk: &Point, id: &[u8]) -> Result<[u8; 32]> {
+    if id.len() > MAX_USER_ID_LEN {
+        return Err(GmError::InputTooLong {
+            max: MAX_USER_ID_LEN,
+            actual: id.len(),
+        });
+    }
+    let curve = EccCtx::new();
+    let mut data = Vec::with_capacity(2 + id.len() + 6 * 32);
+    data.extend_from_slice(&((id.len() * 8) as u16).to_be_bytes());
+    data.extend_from_slice(id);
+    data.extend_from_slice(&curve.get_a().to_bytes());
+    data.extend_from_slice(&curve.get_b().to_bytes());
+    for point in [&curve.generator().map_err(|e| libsm_error("ZA", e))?, pk] {
+        let (x, y) = curve
+            .to_affine(point)
+            .map_err(|e| GmError::InvalidPublicKey(e.to_string()))?;
+        data.extend_from_slice(&x.to_bytes());
+        data.extend_from_slice(&y.to_bytes());
+    }
+    Ok(sm3::digest(&data))
+}
+
+/// `e = SM3(ZA || M)`, the value actually signed.
+pub fn message_hash(za: &[u8; 32], msg: &[u8]) -> [u8; 32] {
+    let mut data = Vec::with_capacity(za.len() + msg.len());
+    data.extend_from_slice(za);
+    data.extend_from_slice(msg);
+    sm3::digest(&data)
+}
+
+/// Sign `msg` as user `id`, returning the DER encoded signature.
+pub fn sign(ctx: &SigCtx, id: &[u8], msg: &[u8], sk: &BigUint, pk: &Point) -> Result<Vec<u8>> {
+    let e = message_hash(&za(pk, id)?, msg);
+    ctx.sign_raw(&e, sk)
+        .map(|sig| sig.der_encode())
+        .map_err(|e| libsm_error("签名", e))
+}
+
+/// Parse a hex random number `k` for [`sign_with_k`] / [`encrypt_with_k`],
+/// which check its range. Whitespace is ignored.
+pub fn parse_k(hex_str: &str) -> Result<BigUint> {
+    let hex_str: String = hex_str.split_whitespace().collect();
+    if hex_str.is_empty() {
+        return Err(GmError::EmptyInput {
+            field: "随机数 k"
+        });
+    }
+    BigUint::from_str_radix(&hex_str, 16).map_err(|_| GmError::InvalidEncoding {
+        field: "随机数 k",
+        encoding: "Hex",
+    })
+}
+
+/// `[k]G` as uncompressed `04 || x || y`: the C1 of an encryption with `k`,
+/// and the point whose x gives `r` when signing.
+pub fn k_point(k: &BigUint) -> Result<Vec<u8>> {
+    let curve = EccCtx::new();
+    if k.is_zero() || k >= curve.get_n() {
+        return Err(GmError::InvalidK);
+    }
+    let point = curve.g_mul(k).map_err(|e| libsm_error("点乘", e))?;
+    Ok([&[0x04], &affine_bytes(&curve, &point)?[..]].concat())
+}
+
+/// Sign `msg` as user `id` with a caller-chosen `k` (1 ≤ k < n), returning
+/// the DER encoded signature.
+///
+/// Only for reproducing known-answer vectors: reusing `k` reveals the private key.
+pub fn sign_with_k(
+    id: &[u8],
+    msg: &[u8],
+    sk: &BigUint,
+    pk: &Point,
+    k: &BigUint,
+) -> Result<Vec<u8>> {
+    let curve = EccCtx::new();
+    let n = curve.get_n();
+    if k.is_zero() || k >= n {
+        return Err(GmError::InvalidK);
+    }
+    let e = BigUint::from_bytes_be(&message_hash(&za(pk, id)?, msg));
+
+    let (x1, _) = curve
+        .g_mul(k)
+        .and_then(|p| curve.to_affine(&p))
+        .map_err(|e| libsm_error("签名", e))?;
+    let r = (e + x1.to_biguint()) % n;
+    if r.is_zero() || &r + k == *n {
+        return Err(GmError::InvalidK);
+    }
+    // s = (1 + d)^-1 * (k - r * d) mod n
+    let inv = curve
+        .inv_n(&(sk + BigUint::one()))
+        .map_err(|e| libsm_error("签名", e))?;
+    let s = (inv * ((k + n) - (&r * sk) % n)) % n;
+    if s.is_zero() {
+        return Err(GmError::InvalidK);
+    }
+    Ok(Signature::new(&r.to_bytes_be(), &s.to_bytes_be()).der_encode())
+}
+
+/// Verify a DER or raw `r || s` signature by user `id` over `msg`.
+pub fn verify(ctx: &SigCtx, id: &[u8], msg: &[u8], pk: &Point, sig: &[u8]) -> Result<bool> {
+    let (signature, _) = decode_signature(sig)?;
+    let e = message_hash(&za(pk, id)?, msg);
+    ctx.verify_raw(&e, pk, &signature)
+        .map_err(|e| libsm_error("验签", e))
+}
+
+/// Decode a signature in either format and check 1 ≤ r, s ≤ n - 1.
+///
+/// A value that parses as DER is taken as DER; otherwise it must be exactly
+/// [`RAW_SIGNATURE_LEN`] bytes.
+pub fn decode_signature(data: &[u8]) -> Result<(Signature, SignatureFormat)> {
+    let (signature, format) = match Signature::der_decode(data) {
+        Ok(signature) => (signature, SignatureFormat::Der),
+        Err(_) if data.len() == RAW_SIGNATURE_LEN => (
+            Signature::new(&data[..32], &data[32..]),
+            SignatureFormat::Raw,
+        ),
+        Err(e) => {
+            return Err(GmError::InvalidSignatureEncoding(format!(
+                "既不是 DER，也不是 {} 字节的 r||s (实际 {} 字节，DER: {:?})",
+                RAW_SIGNATURE_LEN,
+                data.len(),
+                e
+            )));
+        }
+    };
+    let curve = EccCtx::new();
+    let n = curve.get_n();
+    for (name, value) in [("r", signature.get_r()), ("s", signature.get_s())] {
+        if value.is_zero() || value >= n {
+            return Err(GmError::InvalidSignatureEncoding(format!(
+                "{} 超出范围: 须满足 1 ≤ {} ≤ n-1",
+                name, name
+            )));
+        }
+    }
+    Ok((signature, format))
+}
+
+/// Serialize a signature whose `r` and `s` are below n.
+pub fn encode_signature(signature: &Signature, format: SignatureFormat) -> Vec<u8> {
+    match format {
+        SignatureFormat::Der => signature.der_encode(),
+        SignatureFormat::Raw => [signature.get_r(), signature.get_s()]
+            .iter()
+            .flat_map(|v| {
+                let bytes = v.to_bytes_be();
+                let mut padded = [0u8; 32];
+                padded[32 - bytes.len()..].copy_from_slice(&bytes);
+                padded
+            })
+            .collect(),
+    }
+}
+
+/// Re-encode a DER or raw signature in `format`.
+pub fn convert_signature(data: &[u8], format: SignatureFormat) -> Result<Vec<u8>> {
+    decode_signature(data).map(|(signature, _)| encode_signature(&signature, format))
+}
+
+/// Encrypt `msg` for `pk` as in GB/T 32918.4, laying the ciphertext out as
+/// `format`. Any non-empty length is supported.
+pub fn encrypt(pk: &Point, msg: &[u8], format: CiphertextFormat) -> Result<Vec<u8>> {
+    if msg.is_empty() {
+        return Err(GmError::EmptyInput {
+            field: "输入数据"
+        });
+    }
+    let curve = EccCtx::new();
+    loop {
+        let k = curve.random_uint();
+        if let Some(parts) = encrypt_with(&curve, pk, msg, &k)? {
+            return Ok(parts.to_bytes(format));
+        }
+    }
+}
+
+/// Encrypt `msg` for `pk` with a caller-chosen `k` (1 ≤ k < n).
+///
+/// Only for reproducing known-answer vectors: with `k` reused for one key,
+/// knowing one plaintext reveals the others.
+pub fn encrypt_with_k(
+    pk: &Point,
+    msg: &[u8],
+    k: &BigUint,
+    format: CiphertextFormat,
+) -> Result<Vec<u8>> {
+    if msg.is_empty() {
+        return Err(GmError::EmptyInput {
+            field: "输入数据"
+        });
+    }
+    let curve = EccCtx::new();
+    if k.is_zero() || k >= curve.get_n() {
+        return Err(GmError::InvalidK);
+    }
+    match encrypt_with(&curve, pk, msg, k)? {
+        Some(parts) => Ok(parts.to_bytes(format)),
+        None => Err(GmError::InvalidK),
+    }
+}
+
+/// Encrypt with the given `k`, or `None` if the KDF output is all zeros and
+/// another `k` must be drawn.
+fn encrypt_with(
+    curve: &EccCtx,
+    pk: &Point,
+    msg: &[u8],
+    k: &BigUint,
+) -> Result<Option<CiphertextParts>> {
+    // C1 = [k]G, (x2, y2) = [k]P; the cofactor h is 1 and P is never
+    // the point at infinity once loaded
+    let error = |e| libsm_error("加密", e);
+    let c1 = affine_bytes(curve, &curve.g_mul(k).map_err(error)?)?;
+    let x2y2 = affine_bytes(curve, &curve.mul(k, pk).map_err(error)?)?;
+    let Some(c2) = mask(&x2y2, msg) else {
+        return Ok(None);
+    };
+    Ok(Some(CiphertextParts {
+        c1,
+        c3: c3(&x2y2, msg).to_vec(),
+        c2,
+    }))
+}
+
+/// Affine `x || y` of `p`, 32 bytes each.
+pub(crate) fn affine_bytes(curve: &EccCtx, p: &Point) -> Result<Vec<u8>> {
+    let (x, y) = curve.to_affine(p).map_err(|e| libsm_error("坐标转换", e))?;
+    Ok([x.to_bytes(), y.to_bytes()].concat())
+}
+
+/// `data ⊕ KDF(x2 || y2, len)`, or `None` if the KDF output is all zeros.
+fn mask(x2y2: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    let t = kdf::key_stream(x2y2, data.len());
+    if t.iter().all(|&b| b == 0) {
+        return None;
+    }
+    Some(data.iter().zip(t).map(|(d, t)| d ^ t).collect())
+}
+
+/// `C3 = SM3(x2 || M || y2)`.
+fn c3(x2y2: &[u8], msg: &[u8]) -> [u8; 32] {
+    let (x2, y2) = x2y2.split_at(32);
+    sm3::digest(&[x2, msg, y2].concat())
+}
+
+/// Decrypt an SM2 ciphertext laid out as `format`.
+///
+/// With `format` of `None` each layout is tried in turn until one parses
+/// and its C3 hash checks out; [`DecryptOutput::format`] reports which.
+pub fn decrypt(
+    sk: &BigUint,
+    data: &[u8],
+    format: Option<CiphertextFormat>,
+) -> Result<DecryptOutput> {
+    let (plaintext, format) = match format {
+        Some(format) => (
+            decrypt_parts(sk, &CiphertextParts::parse(data, format)?)?,
+            format,
+        ),
+        None => decrypt_any_format(sk, data)?,
+    };
+
+    Ok(DecryptOutput { plaintext, format })
+}
+
+/// Try each layout in turn until one parses and decrypts.
+fn decrypt_any_format(sk: &BigUint, data: &[u8]) -> Result<(Vec<u8>, CiphertextFormat)> {
+    let mut error = None;
+    let mut parsed = false;
+    for format in CiphertextFormat::ALL {
+        match CiphertextParts::parse(data, format) {
+            Ok(parts) => match decrypt_parts(sk, &parts) {
+                Ok(plaintext) => return Ok((plaintext, format)),
+                // A layout that parsed has the more telling error
+                Err(e) if !parsed => (error, parsed) = (Some(e), true),
+                Err(_) => {}
+            },
+            Err(e) if error.is_none() => error = Some(e),
+            Err(_) => {}
+        }
+    }
+    Err(error.expect("at least one layout tried"))
+}
+
+fn decrypt_parts(sk: &BigUint, parts: &CiphertextParts) -> Result<Vec<u8>> {
+    if parts.c2.is_empty() {
+        return Err(GmError::InvalidCiphertext("C2 为空".into()));
+    }
+    let curve = EccCtx::new();
+    let point = curve
+        .mul(sk, &parts.c1_point()?)
+        .map_err(|e| libsm_error("解密", e))?;
+    let x2y2 = affine_bytes(&curve, &point)?;
+    let msg = mask(&x2y2, &parts.c2)
+        .ok_or_else(|| GmError::InvalidCiphertext("KDF 输出全为 0".into()))?;
+    if c3(&x2y2, &msg)[..] != parts.c3[..] {
+        return Err(GmError::CiphertextHashMismatch);
+    }
+    Ok(msg)
+}
+
+pub(crate) fn libsm_error(op: &'static str, e: impl std::fmt::Display) -> GmError {
+    GmError::Libsm {
+        op,
+        detail: e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_verify_roundtrip() {
+        let ctx = SigCtx::new();
+        let (pk, sk) = generate_keypair(&ctx).unwrap();
+        let id = DEFAULT_USER_ID.as_bytes();
+        let sig = sign(&ctx, id, b"message digest", &sk, &pk).unwrap();
+        assert!(verify(&ctx, id, b"message digest", &pk, &sig).unwrap());
+        assert!(!verify(&ctx, id, b"message digesT", &pk, &sig).unwrap());
+        assert!(!verify(&ctx, b"ALICE123@YAHOO.COM", b"message digest", &pk, &sig).unwrap());
+        // libsm's own sign/verify use the default ID
+        let libsm_sig = ctx.sign(b"message digest", &sk, &pk).unwrap().der_encode();
+        assert!(verify(&ctx, id, b"message digest", &pk, &libsm_sig).unwrap());
+    }
+
+    #[test]
+    fn test_signature_formats() {
+        let ctx = SigCtx::new();
+        let (pk, sk) = generate_keypair(&ctx).unwrap();
+        let der = sign(&ctx, b"id", b"abc", &sk, &pk).unwrap();
+        let raw = convert_signature(&der, SignatureFormat::Raw).unwrap();
+        assert_eq!(raw.len(), RAW_SIGNATURE_LEN);
+        assert!(verify(&ctx, b"id", b"abc", &pk, &raw).unwrap());
+        assert_eq!(convert_signature(&raw, SignatureFormat::Der).unwrap(), der);
+        assert_eq!(decode_signature(&raw).unwrap().1, SignatureFormat::Raw);
+        assert_eq!(decode_signature(&der).unwrap().1, SignatureFormat::Der);
+
+        // Small r keeps its leading zeros in raw form
+        let small = Signature::new(&[1], &[2]);
+        let raw = encode_signature(&small, SignatureFormat::Raw);
+        assert_eq!(raw[31], 1);
+        assert_eq!(raw[63], 2);
+        assert_eq!(raw.iter().filter(|&&b| b != 0).count(), 2);
+
+        let n = EccCtx::new().get_n().to_bytes_be();
+        let out_of_range = [&n[..], &[1; 32][..]].concat();
+        assert!(matches!(
+            decode_signature(&out_of_range),
+            Err(GmError::InvalidSignatureEncoding(detail)) if detail.starts_with("r ")
+        ));
+        assert!(decode_signature(&[0; 64]).is_err());
+        assert!(decode_signature(&raw[..63]).is_err());
+    }
+
+    #[test]
+    fn test_fixed_k_rejects_out_of_range_k() {
+        let ctx = SigCtx::new();
+        let (pk, sk) = generate_keypair(&ctx).unwrap();
+        let n = BigUint::from_str_radix(
+            "FFFFFFFEFFFFFFFFFFFFFFFFFFFFFFFF7203DF6B21C6052B53BBF40939D54123",
+            16,
+        )
+        .unwrap();
+        for k in [BigUint::zero(), n] {
+            assert_eq!(
+                sign_with_k(b"id", b"abc", &sk, &pk, &k),
+                Err(GmError::InvalidK)
+            );
+            assert_eq!(
+                encrypt_with_k(&pk, b"abc", &k, CiphertextFormat::default()),
+                Err(GmError::InvalidK)
+            );
+        }
+        let sig = sign_with_k(b"id", b"abc", &sk, &pk, &BigUint::from(7u32)).unwrap();
+        assert!(verify(&ctx, b"id", b"abc", &pk, &sig).unwrap());
+        assert_eq!(parse_k(" 0a \n"), Ok(BigUint::from(10u32)));
+        assert!(parse_k("xyz").is_err());
+    }
+
+    #[test]
+    fn test_parse_public_key_forms() {
+        let ctx = SigCtx::new();
+        let (pk, _) = generate_keypair(&ctx).unwrap();
+        let full = public_key_to_hex(&ctx, &pk).unwrap();
+        let compressed = hex::encode(ctx.serialize_pubkey(&pk, true).unwrap());
+        for text in [&full, &compressed, &full[2..].to_string()] {
+            let parsed = parse_public_key(&ctx, text).unwrap();
+            assert_eq!(public_key_to_hex(&ctx, &parsed).unwrap(), full);
+        }
+        let wrapped = format!(" {}\n{} ", &full[..66], &full[66..]);
+        assert!(parse_public_key(&ctx, &wrapped).is_ok());
+
+        let invalid = |text: &str| match parse_public_key(&ctx, text) {
+            Err(GmError::InvalidPublicKey(detail)) => detail,
+            other => panic!("{:?} accepted: {:?}", text, other.map(|_| ())),
+        };
+        let mut off_curve = full.clone();
+        off_curve.replace_range(129.., if full.ends_with('0') { "1" } else { "0" });
+        assert!(invalid(&off_curve).contains("曲线"));
+        assert!(invalid(&format!("05{}", &full[2..])).contains("前缀"));
+        assert!(invalid(&full[..126]).contains("实际 63 字节"));
+        assert!(invalid(&format!("04{}{}", "ff".repeat(32), &full[66..])).contains("p"));
+        assert!(invalid("04zz").contains("Hex"));
+    }
+
+    #[test]
+    fn test_za_and_message_hash() {
+        let ctx = SigCtx::new();
+        let pk = parse_public_key(
+            &ctx,
+            "0409f9df311e5421a150dd7d161e4bc5c672179fad1833fc076bb08ff356f35020\
+             ccea490ce26775a52dc6ea718cc1aa600aed05fbf35e084a6632f6072da9ad13",
+        )
+        .unwrap();
+        // Computed independently from the curve parameters
+        assert_eq!(
+            hex::encode(za(&pk, b"ALICE123@YAHOO.COM").unwrap()),
+            "26db4bc1839bd22e97e1dab667ec5e0a730d5e16521398b4435c576a93afd7ed"
+        );
+        let za = za(&pk, DEFAULT_USER_ID.as_bytes()).unwrap();
+        assert_eq!(
+            hex::encode(za),
+            "b2e14c5c79c6df5b85f4fe7ed8db7a262b9da7e07ccb0ea9f4747b8ccda8a4f3"
+        );
+        assert_eq!(
+            message_hash(&za, b"message digest"),
+            ctx.hash(DEFAULT_USER_ID, &pk, b"message digest").unwrap()
+        );
+        assert_eq!(
+            super::za(&pk, &[0; MAX_USER_ID_LEN + 1]),
+            Err(GmError::InputTooLong {
+                max: MAX_USER_ID_LEN,
+                actual: MAX_USER_ID_LEN + 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_key_hex_encoding() {
+        let ctx = SigCtx::new();
+        let (pk, sk) = generate_keypair(&ctx).unwrap();
+        let pk_hex = public_key_to_hex(&ctx, &pk).unwrap();
+        assert_eq!(pk_hex.len(), 130);
+        assert!(pk_hex.starts_with("04"));
+        let loaded = ctx.load_pubkey(&hex::decode(&pk_hex).unwrap()).unwrap();
+        assert_eq!(public_key_to_hex(&ctx, &loaded).unwrap(), pk_hex);
+        assert_eq!(parse_private_key(&private_key_to_hex(&sk)).unwrap(), sk);
+        let derived = public_key_from_private(&sk).unwrap();
+        assert_eq!(public_key_to_hex(&ctx, &derived).unwrap(), pk_hex);
+    }
+
+    #[test]
+    fn test_private_key_range() {
+        let ctx = SigCtx::new();
+        // GB/T 32918.5 example key pair
+        let sk =
+            parse_private_key("3945208F7B2144B13F36E38AC6D39F95\n889393692860B51A42FB81EF4DF7C5B8")
+                .unwrap();
+        assert_eq!(
+            public_key_to_hex(&ctx, &public_key_from_private(&sk).unwrap()).unwrap(),
+            "0409f9df311e5421a150dd7d161e4bc5c672179fad1833fc076bb08ff356f35020\
+             ccea490ce26775a52dc6ea718cc1aa600aed05fbf35e084a6632f6072da9ad13"
+        );
+
+        let n_minus = |k: u32| {
+            let n = EccCtx::new().get_n().clone();
+            (n - k).to_str_radix(16)
+        };
+        assert!(parse_private_key(&n_minus(2)).is_ok());
+        assert!(parse_private_key("1").is_ok());
+        for text in ["0", &n_minus(1), &n_minus(0)] {
+            assert_eq!(parse_private_key(text), Err(GmError::PrivateKeyOutOfRange));
+        }
+        assert_eq!(parse_private_key("xyz"), Err(GmError::InvalidPrivateKey));
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_short() {
+        let ctx = SigCtx::new();
+        let (pk, sk) = generate_keypair(&ctx).unwrap();
+        let out = encrypt(&pk, b"abc", CiphertextFormat::C1C2C3).unwrap();
+        assert_eq!(out.len(), 65 + 3 + 32);
+        let dec = decrypt(&sk, &out, None).unwrap();
+        assert_eq!(dec.plaintext, b"abc");
+        assert_eq!(dec.format, CiphertextFormat::C1C2C3);
+
+        assert_eq!(
+            decrypt(&sk, &out[..96], None).err(),
+            Some(GmError::CiphertextTooShort { len: 96, min: 97 })
+        );
+        assert_eq!(
+            encrypt(&pk, b"", CiphertextFormat::C1C2C3).err(),
+            Some(GmError::EmptyInput {
+                field: "输入数据"
+            })
+        );
+    }
+
+    #[test]
+    fn test_encrypt_every_short_length() {
+        let ctx = SigCtx::new();
+        let (pk, sk) = generate_keypair(&ctx).unwrap();
+        for len in 1..=64 {
+            // Plaintexts that look PKCS#7 padded must come back unchanged
+            let msg = vec![len as u8; len];
+            let ciphertext = encrypt(&pk, &msg, CiphertextFormat::C1C3C2).unwrap();
+            assert_eq!(ciphertext.len(), 65 + 32 + len);
+            let dec = decrypt(&sk, &ciphertext, Some(CiphertextFormat::C1C3C2)).unwrap();
+            assert_eq!(dec.plaintext, msg, "length {}", len);
+
+            let mut tampered = ciphertext;
+            *tampered.last_mut().unwrap() ^= 1;
+            assert_eq!(
+                decrypt(&sk, &tampered, Some(CiphertextFormat::C1C3C2)).err(),
+                Some(GmError::CiphertextHashMismatch)
+            );
+        }
+    }
+
+    #[test]
+    fn test_encrypt_with_standard_k() {
+        // GB/T 32918.5-2017 encryption example on the recommended curve
+        let ctx = SigCtx::new();
+        let pk = parse_public_key(
+            &ctx,
+            "0409F9DF311E5421A150DD7D161E4BC5C672179FAD1833FC076BB08FF356F35020\
+             CCEA490CE26775A52DC6EA718CC1AA600AED05FBF35E084A6632F6072DA9AD13",
+        )
+        .unwrap();
+        let k = BigUint::from_str_radix(
+            "59276E27D506861A16680F3AD9C02DCCEF3CC1FA3CDBE4CE6D54B80DEAC1BC21",
+            16,
+        )
+        .unwrap();
+        let ciphertext =
+            encrypt_with_k(&pk, b"encryption standard", &k, CiphertextFormat::C1C3C2).unwrap();
+        assert_eq!(&ciphertext[..65], &k_point(&k).unwrap()[..]);
+        assert_eq!(
+            hex::encode(ciphertext),
+            "0404ebfc718e8d1798620432268e77feb6415e2ede0e073c0f4f640ecd2e149a73\
+             e858f9d81e5430a57b36daab8f950a3c64e6ee6a63094d99283aff767e124df0\
+             59983c18f809e262923c53aec295d30383b54e39d609d160afcb1908d0bd8766\
+             21886ca989ca9c7d58087307ca93092d651efa"
+        );
+    }
+
+    #[test]
+    fn test_ciphertext_formats() {
+        let ctx = SigCtx::new();
+        let (pk, sk) = generate_keypair(&ctx).unwrap();
+        let msg = b"0123456789abcdef0123456789abcdef";
+        let out = encrypt(&pk, msg, CiphertextFormat::Asn1).unwrap();
+        assert_eq!(out[0], 0x30);
+
+        for format in CiphertextFormat::ALL {
+            let data = convert_ciphertext(&out, CiphertextFormat::Asn1, format).unwrap();
+            let expected_len = match format {
+                CiphertextFormat::C1C3C2 | CiphertextFormat::C1C2C3 => 65 + 32 + msg.len(),
+                CiphertextFormat::C1C3C2Bare | CiphertextFormat::C1C2C3Bare => 64 + 32 + msg.len(),
+                CiphertextFormat::Asn1 => out.len(),
+            };
+            assert_eq!(data.len(), expected_len, "{:?}", format);
+            let dec = decrypt(&sk, &data, None).unwrap();
+            assert_eq!((dec.plaintext.as_slice(), dec.format), (&msg[..], format));
+            assert_eq!(decrypt(&sk, &data, Some(format)).unwrap().plaintext, msg);
+            assert_eq!(
+                convert_ciphertext(&data, format, CiphertextFormat::Asn1).unwrap(),
+                out
+            );
+        }
+
+        // The wrong order parses but fails the C3 check
+        let c1c3c2 =
+            convert_ciphertext(&out, CiphertextFormat::Asn1, CiphertextFormat::C1C3C2).unwrap();
+        assert!(decrypt(&sk, &c1c3c2, Some(CiphertextFormat::C1C2C3)).is_err());
+        // The wrong prefix puts C1 off the curve
+        assert!(matches!(
+            convert_ciphertext(
+                &c1c3c2,
+                CiphertextFormat::C1C3C2Bare,
+                CiphertextFormat::Asn1
+            ),
+            Err(GmError::InvalidCiphertext(_))
+        ));
+        let mut bad_prefix = c1c3c2.clone();
+        bad_prefix[0] = 0x05;
+        assert!(matches!(
+            convert_ciphertext(&bad_prefix, CiphertextFormat::C1C3C2, CiphertextFormat::Asn1),
+            Err(GmError::InvalidCiphertext(detail)) if detail.contains("04")
+        ));
+    }
+}
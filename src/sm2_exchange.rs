@@ -0,0 +1,353 @@
+//! SM2 密钥交换协议 (GB/T 32918.3-2016) 的双方模拟
+//!
+//! 同一进程内依次计算 A (发起方) 与 B (响应方) 的全部中间值，便于与设备实现逐项核对。
+//! libsm 自带的 `exchange` 模块把确认值前缀 0x02 / 0x03 写成两个字节，与标准不符，
+//! 且不公开中间值，这里直接用 `EccCtx` 的点运算实现。
+
+use std::fmt::Write as _;
+
+use libsm::sm2::ecc::EccCtx;
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+
+use crate::error::{GmError, Result};
+use crate::sm2::{self, Point, affine_bytes, libsm_error};
+use crate::{kdf, sm3};
+
+/// Longest shared key [`exchange`] derives, in bytes.
+pub const MAX_KEY_LEN: usize = 1024;
+
+/// `w = ⌈⌈log2(n)⌉ / 2⌉ - 1` for the 256-bit order of the recommended curve.
+const W: u32 = 127;
+
+/// One side's static key pair, identity and ephemeral private key.
+pub struct Party {
+    pub id: Vec<u8>,
+    pub sk: BigUint,
+    pub pk: Point,
+    /// Ephemeral private key `r` (1 ≤ r ≤ n-1); `None` draws a random one.
+    pub r: Option<BigUint>,
+}
+
+/// Everything one side computes.
+pub struct Side {
+    /// Ephemeral private key actually used.
+    pub r: BigUint,
+    /// `R = [r]G` as `x || y`.
+    pub r_point: Vec<u8>,
+    /// `x̄ = 2^w + (x & (2^w - 1))` of this side's `R`.
+    pub x_bar: BigUint,
+    /// `t = (d + x̄·r) mod n`.
+    pub t: BigUint,
+    /// Shared point `[h·t](P' + [x̄']R')` as `x || y`: U for A, V for B.
+    pub shared: Vec<u8>,
+    /// `KDF(x || y || ZA || ZB, klen)`.
+    pub key: Vec<u8>,
+    /// `SM3(0x02 || y || SM3(x || ZA || ZB || x1 || y1 || x2 || y2))`: S1 for A, SB for B.
+    pub s2: [u8; 32],
+    /// The same with prefix 0x03: SA for A, S2 for B.
+    pub s3: [u8; 32],
+}
+
+/// Result of [`exchange`].
+pub struct Exchange {
+    pub za: [u8; 32],
+    pub zb: [u8; 32],
+    pub a: Side,
+    pub b: Side,
+}
+
+/// Run the key exchange between initiator `a` and responder `b`, deriving
+/// `key_len` bytes (at most [`MAX_KEY_LEN`]).
+pub fn exchange(a: &Party, b: &Party, key_len: usize) -> Result<Exchange> {
+    if key_len == 0 {
+        return Err(GmError::EmptyInput {
+            field: "协商密钥长度",
+        });
+    }
+    if key_len > MAX_KEY_LEN {
+        return Err(GmError::InvalidOutputLength {
+            max: MAX_KEY_LEN,
+            actual: key_len,
+        });
+    }
+    let curve = EccCtx::new();
+    let ra = ephemeral_key(&curve, a.r.as_ref(), "r_A")?;
+    let rb = ephemeral_key(&curve, b.r.as_ref(), "r_B")?;
+    let error = |e| libsm_error("密钥交换", e);
+    let ra_point = curve.g_mul(&ra).map_err(error)?;
+    let rb_point = curve.g_mul(&rb).map_err(error)?;
+
+    let za = sm2::za(&a.pk, &a.id)?;
+    let zb = sm2::za(&b.pk, &b.id)?;
+    let z = [za, zb].concat();
+    Ok(Exchange {
+        za,
+        zb,
+        a: derive(
+            &curve, &a.sk, ra, &ra_point, &b.pk, &rb_point, &z, true, key_len,
+        )?,
+        b: derive(
+            &curve, &b.sk, rb, &rb_point, &a.pk, &ra_point, &z, false, key_len,
+        )?,
+    })
+}
+
+/// Parse a hex ephemeral private key named `name`; blank gives `None`
+/// (random). The range is checked by [`exchange`].
+pub fn parse_ephemeral_key(hex_str: &str, name: &str) -> Result<Option<BigUint>> {
+    let hex_str: String = hex_str.split_whitespace().collect();
+    if hex_str.is_empty() {
+        return Ok(None);
+    }
+    BigUint::parse_bytes(hex_str.as_bytes(), 16)
+        .map(Some)
+        .ok_or_else(|| GmError::KeyExchange(format!("临时私钥 {} 不是合法的 Hex", name)))
+}
+
+/// `r` if it satisfies 1 ≤ r ≤ n-1, or a random one.
+fn ephemeral_key(curve: &EccCtx, r: Option<&BigUint>, name: &str) -> Result<BigUint> {
+    match r {
+        None => Ok(curve.random_uint()),
+        Some(r) if r.is_zero() || r >= curve.get_n() => Err(GmError::KeyExchange(format!(
+            "临时私钥 {} 超出范围: 须满足 1 ≤ {} ≤ n-1",
+            name, name
+        ))),
+        Some(r) => Ok(r.clone()),
+    }
+}
+
+/// `2^w + (x & (2^w - 1))` of the affine `x || y` bytes of a point.
+fn x_bar(xy: &[u8]) -> BigUint {
+    let low = (BigUint::one() << W) - 1u32;
+    (BigUint::one() << W) + (BigUint::from_bytes_be(&xy[..32]) & low)
+}
+
+/// One side's values from its own keys and the other side's public values.
+/// `z` is `ZA || ZB`.
+#[allow(clippy::too_many_arguments)]
+fn derive(
+    curve: &EccCtx,
+    sk: &BigUint,
+    r: BigUint,
+    r_point: &Point,
+    peer_pk: &Point,
+    peer_r_point: &Point,
+    z: &[u8],
+    initiator: bool,
+    key_len: usize,
+) -> Result<Side> {
+    let error = |e| libsm_error("密钥交换", e);
+    let own = affine_bytes(curve, r_point)?;
+    let peer = affine_bytes(curve, peer_r_point)?;
+    let x_bar_own = x_bar(&own);
+    let t = (sk + &x_bar_own * &r) % curve.get_n();
+
+    // [h·t](P' + [x̄']R') with cofactor h = 1
+    let sum = curve
+        .mul(&x_bar(&peer), peer_r_point)
+        .and_then(|p| curve.add(peer_pk, &p))
+        .map_err(error)?;
+    let point = curve.mul(&t, &sum).map_err(error)?;
+    if point.is_zero() {
+        return Err(GmError::KeyExchange("共享点为无穷远点".into()));
+    }
+    let shared = affine_bytes(curve, &point)?;
+    let (x, y) = shared.split_at(32);
+    let key = kdf::sm2_kdf(&[&shared, z].concat(), key_len)?;
+
+    let (a_point, b_point) = match initiator {
+        true => (&own, &peer),
+        false => (&peer, &own),
+    };
+    let inner = sm3::digest(&[x, z, a_point, b_point].concat());
+    let confirm = |prefix: u8| sm3::digest(&[&[prefix], y, &inner].concat());
+    Ok(Side {
+        r,
+        r_point: own,
+        x_bar: x_bar_own,
+        t,
+        key,
+        s2: confirm(0x02),
+        s3: confirm(0x03),
+        shared,
+    })
+}
+
+impl Exchange {
+    /// Both sides derived the same key and their confirmation hashes match.
+    pub fn agreed(&self) -> bool {
+        self.a.key == self.b.key && self.a.s2 == self.b.s2 && self.a.s3 == self.b.s3
+    }
+
+    /// Every value as plain text; `confirm` adds the optional hashes S1/SB/S2/SA.
+    pub fn to_text(&self, confirm: bool) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "SM2 密钥交换 (GB/T 32918.3-2016)");
+        let _ = writeln!(out, "ZA = {}", hex::encode(self.za));
+        let _ = writeln!(out, "ZB = {}", hex::encode(self.zb));
+        let sides = [
+            ("A (发起方)", "A", "1", "U", "B", "2", ["S1", "SA"]),
+            ("B (响应方)", "B", "2", "V", "A", "1", ["SB", "S2"]),
+        ];
+        for (side, (title, me, i, point, peer, j, s_names)) in
+            [&self.a, &self.b].into_iter().zip(sides)
+        {
+            let (x, y) = side.r_point.split_at(32);
+            let (xs, ys) = side.shared.split_at(32);
+            let _ = writeln!(out, "\n== {} ==", title);
+            let _ = writeln!(out, "r_{} = {:064x}", me, side.r);
+            let _ = writeln!(out, "R_{} = [r_{}]G", me, me);
+            let _ = writeln!(out, "  x{} = {}", i, hex::encode(x));
+            let _ = writeln!(out, "  y{} = {}", i, hex::encode(y));
+            let _ = writeln!(out, "x̄{} = {:x}", i, side.x_bar);
+            let _ = writeln!(
+                out,
+                "t_{} = (d_{} + x̄{}·r_{}) mod n = {:064x}",
+                me, me, i, me, side.t
+            );
+            let _ = writeln!(
+                out,
+                "{} = [h·t_{}](P_{} + [x̄{}]R_{})",
+                point, me, peer, j, peer
+            );
+            let _ = writeln!(out, "  x{} = {}", point, hex::encode(xs));
+            let _ = writeln!(out, "  y{} = {}", point, hex::encode(ys));
+            let _ = writeln!(
+                out,
+                "K_{} = KDF(x{}||y{}||ZA||ZB, {}) = {}",
+                me,
+                point,
+                point,
+                side.key.len() * 8,
+                hex::encode(&side.key)
+            );
+            if confirm {
+                for (name, (prefix, value)) in
+                    s_names.into_iter().zip([("02", side.s2), ("03", side.s3)])
+                {
+                    let _ = writeln!(
+                        out,
+                        "{} = SM3(0x{}||y{}||SM3(x{}||ZA||ZB||x1||y1||x2||y2)) = {}",
+                        name,
+                        prefix,
+                        point,
+                        point,
+                        hex::encode(value)
+                    );
+                }
+            }
+        }
+        let verdict = |same: bool| if same { "一致" } else { "不一致" };
+        let _ = writeln!(out, "\n== 结果 ==");
+        let _ = writeln!(out, "K_A = K_B: {}", verdict(self.a.key == self.b.key));
+        if confirm {
+            let _ = writeln!(out, "S1 = SB: {}", verdict(self.a.s2 == self.b.s2));
+            let _ = writeln!(out, "S2 = SA: {}", verdict(self.a.s3 == self.b.s3));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sm2::SigCtx;
+
+    fn party(id: &[u8], d: &str, r: Option<&str>) -> Party {
+        let sk = sm2::parse_private_key(d).unwrap();
+        Party {
+            id: id.to_vec(),
+            pk: sm2::public_key_from_private(&sk).unwrap(),
+            sk,
+            r: r.map(|r| BigUint::parse_bytes(r.as_bytes(), 16).unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_fixed_ephemeral_keys() {
+        // Expected values from an independent implementation of GB/T 32918.3
+        let a = party(
+            b"ALICE123@YAHOO.COM",
+            "6fcba2ef9ae0ab902bc3bde3ff915d44ba4cc78f88e2f8e7f8996d3b8cceedee",
+            Some("83a2c9c8b96e5af70bd480b472409a9a327257f1ebb73f5b073354b248668563"),
+        );
+        let b = party(
+            b"BILL456@YAHOO.COM",
+            "5e35d7d3f3c54dbac72e61819e730b019a84208ca3a35e4c2e353dfccb2a3b53",
+            Some("33fe21940342161c55619c4a0c060293d543c80af19748ce176d83477de71c80"),
+        );
+        let result = exchange(&a, &b, 16).unwrap();
+        assert!(result.agreed());
+        assert_eq!(
+            hex::encode(result.za),
+            "5d0b1182e1cee9093e96b6bdbf2e32f854d5c753e2ce9865d4984b8b70a8ae2b"
+        );
+        assert_eq!(
+            hex::encode(&result.b.r_point[..32]),
+            "26891afec73a32fa5bf2cbe91acded37cac48621d85d5965a5044a84dbda5988"
+        );
+        assert_eq!(
+            format!("{:064x}", result.a.t),
+            "a4873bd4ce9a316dcd19b3b8197d1db852a16e8c40561dde2efbb3bb1f3fa297"
+        );
+        assert_eq!(
+            hex::encode(&result.b.shared[..32]),
+            "588d82da2f0e75a042aab58a707c725e203d1bf1cb8eff56e37af32c24665267"
+        );
+        assert_eq!(
+            hex::encode(&result.b.key),
+            "f02f9068ad13e14f2b2602e0dfb2504f"
+        );
+        assert_eq!(
+            hex::encode(result.b.s2),
+            "b93374ade30a74e12ddb40e4c03d0c6fcf61badbf2c2c5cc39a91201d9228e2e"
+        );
+        assert_eq!(
+            hex::encode(result.b.s3),
+            "daefca6c32f53c48444d4ef35f98471e5d1cd1e3b5b8e3322dede310306689f6"
+        );
+
+        let text = result.to_text(true);
+        assert!(text.contains("K_A = K_B: 一致"));
+        assert!(text.contains("S2 = SA: 一致"));
+        assert!(!result.to_text(false).contains("S1"));
+    }
+
+    #[test]
+    fn test_random_and_invalid_inputs() {
+        let ctx = SigCtx::new();
+        let (pk, sk) = sm2::generate_keypair(&ctx).unwrap();
+        let a = Party {
+            id: sm2::DEFAULT_USER_ID.as_bytes().to_vec(),
+            sk,
+            pk,
+            r: None,
+        };
+        let b = party(b"B", "01", None);
+        let result = exchange(&a, &b, 100).unwrap();
+        assert!(result.agreed());
+        assert_eq!(result.a.key.len(), 100);
+
+        // B holding a different static key than A expects derives another key
+        let impostor = Party {
+            pk: a.pk,
+            ..party(b"B", "02", Some("01"))
+        };
+        assert!(!exchange(&a, &impostor, 16).unwrap().agreed());
+
+        let zero = party(b"B", "01", Some("00"));
+        assert!(matches!(
+            exchange(&a, &zero, 16),
+            Err(GmError::KeyExchange(_))
+        ));
+        assert!(matches!(
+            exchange(&a, &b, 0),
+            Err(GmError::EmptyInput { .. })
+        ));
+        assert!(matches!(
+            exchange(&a, &b, MAX_KEY_LEN + 1),
+            Err(GmError::InvalidOutputLength { .. })
+        ));
+    }
+}
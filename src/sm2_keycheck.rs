@@ -0,0 +1,317 @@
+//! SM2 密钥与公钥点的逐项检查，给出每一项失败的原因
+//!
+//! 与 [`crate::sm2::parse_private_key`] / [`crate::sm2::parse_public_key`] 的条件相同，
+//! 但不在第一处错误就返回，曲线方程与点的阶也在这里独立验算，便于排查对方提供的密钥。
+
+use std::fmt::Write as _;
+
+use libsm::sm2::ecc::EccCtx;
+use num_bigint::BigUint;
+use num_traits::{Num, Zero};
+
+use crate::error::{GmError, Result};
+use crate::sm2::{self, Point, SigCtx, affine_bytes, libsm_error};
+
+/// Outcome of one check.
+pub struct Check {
+    pub name: &'static str,
+    /// `Ok` holds a note (possibly empty); `Err` explains the failure.
+    pub outcome: std::result::Result<String, String>,
+}
+
+impl Check {
+    pub fn passed(&self) -> bool {
+        self.outcome.is_ok()
+    }
+}
+
+/// Every check run by [`check_keys`], in order. The checks of one key stop
+/// at its first failure, since later ones depend on it.
+pub struct KeyCheck {
+    pub checks: Vec<Check>,
+}
+
+/// Check a hex private key and / or public key; a blank one is not checked,
+/// and the pair is only compared when both pass.
+pub fn check_keys(ctx: &SigCtx, private_key: &str, public_key: &str) -> Result<KeyCheck> {
+    let private_key: String = private_key.split_whitespace().collect();
+    let public_key: String = public_key.split_whitespace().collect();
+    if private_key.is_empty() && public_key.is_empty() {
+        return Err(GmError::EmptyInput {
+            field: "私钥或公钥",
+        });
+    }
+    let curve = EccCtx::new();
+    let mut checks = Vec::new();
+    let sk = match private_key.is_empty() {
+        true => None,
+        false => check_private_key(&curve, &private_key, &mut checks),
+    };
+    let pk = match public_key.is_empty() {
+        true => None,
+        false => check_public_key(ctx, &curve, &public_key, &mut checks)?,
+    };
+    if let (Some(sk), Some(pk)) = (sk, pk) {
+        let derived = affine_bytes(&curve, &sm2::public_key_from_private(&sk)?)?;
+        let given = affine_bytes(&curve, &pk)?;
+        checks.push(Check {
+            name: "私钥与公钥匹配 (P = [d]G)",
+            outcome: match derived == given {
+                true => Ok(String::new()),
+                false => Err(format!(
+                    "[d]G = 04{}，与给出的公钥不同：两者不是同一密钥对",
+                    hex::encode(derived)
+                )),
+            },
+        });
+    }
+    Ok(KeyCheck { checks })
+}
+
+/// Push `name` with `outcome` and return whether it passed.
+fn record(
+    checks: &mut Vec<Check>,
+    name: &'static str,
+    outcome: std::result::Result<String, String>,
+) -> bool {
+    let passed = outcome.is_ok();
+    checks.push(Check { name, outcome });
+    passed
+}
+
+fn check_private_key(curve: &EccCtx, hex_str: &str, checks: &mut Vec<Check>) -> Option<BigUint> {
+    let parsed = BigUint::from_str_radix(hex_str, 16);
+    let outcome = match &parsed {
+        Ok(_) if hex_str.len() > 64 => Ok(format!("{} 个 Hex 字符，多于 64 个", hex_str.len())),
+        Ok(_) => Ok(String::new()),
+        Err(_) => Err("含有非 Hex 字符".to_string()),
+    };
+    if !record(checks, "私钥为 Hex 整数", outcome) {
+        return None;
+    }
+    let sk = parsed.ok()?;
+    let n = curve.get_n();
+    let outcome = if sk.is_zero() {
+        Err("d = 0".to_string())
+    } else if &sk >= n {
+        Err("d ≥ n：超出基点的阶，应先模 n 约简，多半是填错了数据".to_string())
+    } else if &sk + 1u32 == *n {
+        Err("d = n-1：签名要求 1 + d 模 n 可逆，因此不可用".to_string())
+    } else {
+        Ok(String::new())
+    };
+    record(checks, "私钥范围 1 ≤ d ≤ n-2", outcome).then_some(sk)
+}
+
+fn check_public_key(
+    ctx: &SigCtx,
+    curve: &EccCtx,
+    hex_str: &str,
+    checks: &mut Vec<Check>,
+) -> Result<Option<Point>> {
+    let Ok(bytes) = hex::decode(hex_str) else {
+        let reason = match hex_str.len() % 2 {
+            1 => "Hex 字符数为奇数",
+            _ => "含有非 Hex 字符",
+        };
+        record(checks, "公钥编码", Err(reason.to_string()));
+        return Ok(None);
+    };
+    let outcome = match (bytes.len(), bytes.first()) {
+        (1, Some(0x00)) => Ok("00: 无穷远点".to_string()),
+        (65, Some(0x04)) => Ok("非压缩 04||X||Y".to_string()),
+        (33, Some(0x02 | 0x03)) => Ok("压缩 02/03||X".to_string()),
+        (64, _) => Ok("无前缀 X||Y".to_string()),
+        (65 | 33, Some(prefix)) => Err(format!(
+            "{} 字节，但前缀 {:02x} 不是 04 (非压缩) 或 02/03 (压缩)",
+            bytes.len(),
+            prefix
+        )),
+        (len, _) => Err(format!(
+            "长度应为 65 (04||X||Y)、33 (02/03||X) 或 64 (X||Y) 字节，实际 {} 字节",
+            len
+        )),
+    };
+    if !record(checks, "公钥编码", outcome) {
+        return Ok(None);
+    }
+    let infinity = bytes == [0x00];
+    let outcome = match infinity {
+        true => Err("公钥是无穷远点 O，不对应任何私钥".to_string()),
+        false => Ok(String::new()),
+    };
+    if !record(checks, "不是无穷远点", outcome) {
+        return Ok(None);
+    }
+
+    let coordinates = match bytes.len() {
+        64 => &bytes[..],
+        _ => &bytes[1..],
+    };
+    let p = BigUint::from_bytes_be(&sm2::FIELD_P);
+    let too_large: Vec<_> = ["x", "y"]
+        .into_iter()
+        .zip(coordinates.chunks(32))
+        .filter(|(_, c)| BigUint::from_bytes_be(c) >= p)
+        .map(|(name, _)| name)
+        .collect();
+    let outcome = match too_large.is_empty() {
+        true => Ok(String::new()),
+        false => Err(format!("{} 不小于素数 p", too_large.join("、"))),
+    };
+    if !record(checks, "坐标小于 p", outcome) {
+        return Ok(None);
+    }
+
+    // Compressed keys are decompressed by libsm, which fails only when no y exists
+    let encoded = match bytes.len() {
+        64 => [&[0x04], &bytes[..]].concat(),
+        _ => bytes,
+    };
+    let Ok(point) = ctx.load_pubkey(&encoded) else {
+        let reason = match encoded[0] {
+            0x04 => "y² ≠ x³ + ax + b (mod p)",
+            _ => "x³ + ax + b 不是模 p 的平方，没有 x 对应的 y",
+        };
+        record(checks, "点在 SM2 曲线上", Err(reason.to_string()));
+        return Ok(None);
+    };
+    let xy = affine_bytes(curve, &point)?;
+    let (x, y) = (
+        BigUint::from_bytes_be(&xy[..32]),
+        BigUint::from_bytes_be(&xy[32..]),
+    );
+    let a = curve.get_a().to_biguint();
+    let b = curve.get_b().to_biguint();
+    let lhs = &y * &y % &p;
+    let rhs = (&x * &x * &x + a * &x + b) % &p;
+    let outcome = match lhs == rhs {
+        true => Ok(String::new()),
+        false => Err(format!("y² = {:x}，x³ + ax + b = {:x} (mod p)", lhs, rhs)),
+    };
+    if !record(checks, "点在 SM2 曲线上", outcome) {
+        return Ok(None);
+    }
+
+    // libsm reduces scalars mod n, so [n]P would always come out as O;
+    // [n-1]P = -P says the same without multiplying by n
+    let n_minus_1 = curve.get_n() - 1u32;
+    let q = curve
+        .mul(&n_minus_1, &point)
+        .map_err(|e| libsm_error("点乘", e))?;
+    let outcome = match is_negation(curve, &point, &q)? {
+        true => Ok("余因子 h = 1，曲线上的点都满足".to_string()),
+        false => Err("[n-1]P ≠ -P，即 [n]P ≠ O".to_string()),
+    };
+    Ok(record(checks, "阶为 n ([n-1]P = -P)", outcome).then_some(point))
+}
+
+/// Whether `q` is `-p`: the same x and y' = p - y (mod p), neither being O.
+fn is_negation(curve: &EccCtx, p: &Point, q: &Point) -> Result<bool> {
+    if p.is_zero() || q.is_zero() {
+        return Ok(false);
+    }
+    let prime = BigUint::from_bytes_be(&sm2::FIELD_P);
+    let (p, q) = (affine_bytes(curve, p)?, affine_bytes(curve, q)?);
+    let neg_y = (&prime - BigUint::from_bytes_be(&p[32..])) % &prime;
+    Ok(p[..32] == q[..32] && BigUint::from_bytes_be(&q[32..]) == neg_y)
+}
+
+impl KeyCheck {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(Check::passed)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for check in &self.checks {
+            let _ = match &check.outcome {
+                Ok(note) if note.is_empty() => writeln!(out, "[通过] {}", check.name),
+                Ok(note) => writeln!(out, "[通过] {} ({})", check.name, note),
+                Err(reason) => writeln!(out, "[失败] {}: {}", check.name, reason),
+            };
+        }
+        let failed = self.checks.iter().filter(|c| !c.passed()).count();
+        let _ = match failed {
+            0 => write!(out, "结论: 全部 {} 项通过", self.checks.len()),
+            n => write!(out, "结论: {} 项未通过 (依赖它的后续检查已跳过)", n),
+        };
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const D: &str = "3945208F7B2144B13F36E38AC6D39F95889393692860B51A42FB81EF4DF7C5B8";
+    const X: &str = "09F9DF311E5421A150DD7D161E4BC5C672179FAD1833FC076BB08FF356F35020";
+    const Y: &str = "CCEA490CE26775A52DC6EA718CC1AA600AED05FBF35E084A6632F6072DA9AD13";
+    const N: &str = "FFFFFFFEFFFFFFFFFFFFFFFFFFFFFFFF7203DF6B21C6052B53BBF40939D54123";
+
+    /// The first failing check, or `None` if all passed.
+    fn failure(private_key: &str, public_key: &str) -> Option<&'static str> {
+        let report = check_keys(&SigCtx::new(), private_key, public_key).unwrap();
+        report.checks.iter().find(|c| !c.passed()).map(|c| c.name)
+    }
+
+    #[test]
+    fn test_valid_keys_pass() {
+        let ctx = SigCtx::new();
+        let public = format!("04{}{}", X, Y);
+        let report = check_keys(&ctx, D, &public).unwrap();
+        assert!(report.passed(), "{}", report.to_text());
+        assert_eq!(report.checks.len(), 8);
+
+        let compressed = hex::encode(
+            ctx.serialize_pubkey(&sm2::parse_public_key(&ctx, &public).unwrap(), true)
+                .unwrap(),
+        );
+        assert_eq!(failure("", &compressed), None);
+        assert_eq!(failure("", &format!("{}\n{}", X, Y)), None);
+        assert!(check_keys(&ctx, " ", "").is_err());
+    }
+
+    #[test]
+    fn test_each_failure_is_named() {
+        let public = format!("04{}{}", X, Y);
+        assert_eq!(failure("xyz", ""), Some("私钥为 Hex 整数"));
+        assert_eq!(failure("0", ""), Some("私钥范围 1 ≤ d ≤ n-2"));
+        let n_minus_1 = format!("{}22", &N[..62]);
+        assert_eq!(failure(&n_minus_1, ""), Some("私钥范围 1 ≤ d ≤ n-2"));
+        assert_eq!(failure(N, ""), Some("私钥范围 1 ≤ d ≤ n-2"));
+
+        assert_eq!(failure("", &format!("05{}{}", X, Y)), Some("公钥编码"));
+        assert_eq!(failure("", &public[..100]), Some("公钥编码"));
+        assert_eq!(failure("", "00"), Some("不是无穷远点"));
+        assert_eq!(
+            failure("", &format!("04{}{}", "FF".repeat(32), Y)),
+            Some("坐标小于 p")
+        );
+        let off_curve = format!("04{}{}", X, X);
+        assert_eq!(failure("", &off_curve), Some("点在 SM2 曲线上"));
+
+        // Both valid, but not a pair
+        let other = "01";
+        assert_eq!(failure(other, &public), Some("私钥与公钥匹配 (P = [d]G)"));
+        // An invalid private key still lets the public key be checked
+        let report = check_keys(&SigCtx::new(), "0", &public).unwrap();
+        assert_eq!(report.checks.iter().filter(|c| c.passed()).count(), 6);
+    }
+
+    #[test]
+    fn test_order_check_can_fail() {
+        // Every point on the curve has order n (h = 1), so the row itself
+        // always passes; its comparison does reject anything but -P
+        let curve = EccCtx::new();
+        let p = sm2::parse_public_key(&SigCtx::new(), &format!("04{}{}", X, Y)).unwrap();
+        let n = curve.get_n();
+        let times = |k: BigUint| curve.mul(&k, &p).unwrap();
+        assert!(is_negation(&curve, &p, &times(n - 1u32)).unwrap());
+        assert!(!is_negation(&curve, &p, &times(n - 2u32)).unwrap());
+        assert!(!is_negation(&curve, &p, &p).unwrap());
+        assert!(!is_negation(&curve, &p, &curve.zero()).unwrap());
+        // libsm reduces the scalar mod n, so [n]P comes out as O for any point
+        assert!(curve.mul(n, &p).unwrap().is_zero());
+    }
+}
@@ -0,0 +1,240 @@
+//! SM3 摘要 (GB/T 32905)
+
+use std::io::Read;
+
+use sm3::{Digest, Sm3};
+use subtle::ConstantTimeEq;
+
+use crate::error::{GmError, Result};
+use crate::stream::{self, Progress};
+
+/// Length of an SM3 digest in bytes.
+pub const DIGEST_LEN: usize = 32;
+/// SM3 input block size in bytes.
+pub const BLOCK_LEN: usize = 64;
+/// Shortest truncated HMAC tag accepted: 80 bits, as RFC 2104 recommends.
+pub const HMAC_MIN_TAG_LEN: usize = 10;
+
+/// Compute the SM3 digest of `data`.
+pub fn digest(data: &[u8]) -> [u8; DIGEST_LEN] {
+    let mut hasher = Sm3::new();
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+/// Compute the SM3 digest of the first `bits` bits of `data` (most
+/// significant bit first), for messages that do not end on a byte boundary.
+pub fn digest_bits(data: &[u8], bits: usize) -> Result<[u8; DIGEST_LEN]> {
+    if bits.is_multiple_of(8) && bits <= data.len() * 8 {
+        return Ok(digest(&data[..bits / 8]));
+    }
+    crate::sm3_trace::digest_bits(data, bits)
+}
+
+/// Compute the SM3 digest of everything read from `reader`, chunk by chunk.
+pub fn digest_reader(reader: impl Read, progress: &Progress) -> Result<[u8; DIGEST_LEN]> {
+    let mut hasher = Sm3::new();
+    stream::for_each_chunk(reader, progress, |chunk| {
+        hasher.update(chunk);
+        Ok(())
+    })?;
+    Ok(hasher.finalize().into())
+}
+
+/// HMAC-SM3 (RFC 2104 / GB/T 15852.2) of `data` under `key`.
+pub fn hmac(key: &[u8], data: &[u8]) -> [u8; DIGEST_LEN] {
+    HmacKey::new(key).mac(data)
+}
+
+/// HMAC-SM3 truncated to its leftmost `tag_len` bytes
+/// (`HMAC_MIN_TAG_LEN..=DIGEST_LEN`).
+pub fn hmac_truncated(key: &[u8], data: &[u8], tag_len: usize) -> Result<Vec<u8>> {
+    truncate_tag(&hmac(key, data), tag_len)
+}
+
+/// The leftmost `tag_len` bytes of a full HMAC (`HMAC_MIN_TAG_LEN..=DIGEST_LEN`).
+pub fn truncate_tag(mac: &[u8; DIGEST_LEN], tag_len: usize) -> Result<Vec<u8>> {
+    check_tag_len(tag_len)?;
+    Ok(mac[..tag_len].to_vec())
+}
+
+/// HMAC-SM3 of everything read from `reader`, chunk by chunk.
+pub fn hmac_reader(key: &[u8], reader: impl Read, progress: &Progress) -> Result<[u8; DIGEST_LEN]> {
+    let hmac_key = HmacKey::new(key);
+    let mut inner = hmac_key.inner.clone();
+    stream::for_each_chunk(reader, progress, |chunk| {
+        inner.update(chunk);
+        Ok(())
+    })?;
+    Ok(hmac_key.finish(inner))
+}
+
+/// Compare an expected tag, possibly truncated, with the leftmost bytes of
+/// the full HMAC `mac`. The comparison takes the same time wherever they differ.
+pub fn hmac_verify(mac: &[u8; DIGEST_LEN], expected: &[u8]) -> Result<bool> {
+    check_tag_len(expected.len())?;
+    Ok(mac[..expected.len()].ct_eq(expected).into())
+}
+
+fn check_tag_len(tag_len: usize) -> Result<()> {
+    if !(HMAC_MIN_TAG_LEN..=DIGEST_LEN).contains(&tag_len) {
+        return Err(GmError::InvalidTagLength {
+            actual: tag_len,
+            min: HMAC_MIN_TAG_LEN,
+            max: DIGEST_LEN,
+        });
+    }
+    Ok(())
+}
+
+/// An HMAC-SM3 key with the padded inner and outer blocks already absorbed,
+/// for MACing many messages under one key (PBKDF2).
+#[derive(Clone)]
+pub(crate) struct HmacKey {
+    inner: Sm3,
+    outer: Sm3,
+}
+
+impl HmacKey {
+    pub(crate) fn new(key: &[u8]) -> Self {
+        let mut block = [0u8; BLOCK_LEN];
+        if key.len() > BLOCK_LEN {
+            block[..DIGEST_LEN].copy_from_slice(&digest(key));
+        } else {
+            block[..key.len()].copy_from_slice(key);
+        }
+        let mut inner = Sm3::new();
+        inner.update(block.map(|b| b ^ 0x36));
+        let mut outer = Sm3::new();
+        outer.update(block.map(|b| b ^ 0x5c));
+        Self { inner, outer }
+    }
+
+    pub(crate) fn mac(&self, data: &[u8]) -> [u8; DIGEST_LEN] {
+        let mut inner = self.inner.clone();
+        inner.update(data);
+        self.finish(inner)
+    }
+
+    fn finish(&self, inner: Sm3) -> [u8; DIGEST_LEN] {
+        let mut outer = self.outer.clone();
+        outer.update(inner.finalize());
+        outer.finalize().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digest_abc() {
+        // GB/T 32905 Appendix A.1
+        assert_eq!(
+            hex::encode(digest(b"abc")),
+            "66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0"
+        );
+    }
+
+    #[test]
+    fn test_hmac() {
+        assert_eq!(
+            hex::encode(hmac(b"key", b"The quick brown fox jumps over the lazy dog")),
+            "bd4a34077888162b210645b8ebf74b9af357303789357a27c7fc457244ebd398"
+        );
+        // Keys longer than one block are hashed first.
+        assert_eq!(
+            hex::encode(hmac(&[b'k'; 100], b"data")),
+            "d2e3191973421dc0792b6ec5e1a5b5c26652d764b024ba8c20221f0396fedfe8"
+        );
+    }
+
+    #[test]
+    fn test_digest_bits() {
+        // Reference values from an independent bit-level implementation
+        assert_eq!(
+            hex::encode(digest_bits(b"abc", 20).unwrap()),
+            "3c67c85bc057fee93dd958542b6abbe5c49b689811ddbf4e8639e2dc495dafbd"
+        );
+        assert_eq!(
+            hex::encode(digest_bits(b"\x7f", 1).unwrap()),
+            "c69de7b7f87f8211f8c0aeaccfd4fe05ecab364e9414040075aeb2046eb7f8ed"
+        );
+        // Bits past the length are ignored; whole bytes match the sm3 crate
+        assert_eq!(
+            digest_bits(b"abc\xff", 20).unwrap(),
+            digest_bits(b"ab\x60", 20).unwrap()
+        );
+        assert_eq!(digest_bits(b"abcd", 24).unwrap(), digest(b"abc"));
+        assert_eq!(digest_bits(&[], 0).unwrap(), digest(&[]));
+
+        let data: Vec<u8> = (0..100).collect();
+        for (bits, expected) in [
+            (
+                447,
+                "1cb34f202cc66fc04509125c3e66b0e13c39b22acb3a93f9894fb04d53f70350",
+            ),
+            (
+                511,
+                "f15bb146ed5199e2382a01eda3f2f1c61351abdb9bfa84e1ba41ff3c44078c61",
+            ),
+            (
+                513,
+                "edefc06bc297bbe0044a76687863a7e5b94c393e14023167f2f34936df432b4a",
+            ),
+            (
+                799,
+                "9611d0c7a98258f9fab3a8fc9d98e5474f7e546a8c31f71a73a0c30bac974c13",
+            ),
+        ] {
+            assert_eq!(
+                hex::encode(digest_bits(&data, bits).unwrap()),
+                expected,
+                "{} bits",
+                bits
+            );
+        }
+        assert_eq!(
+            digest_bits(b"abc", 25),
+            Err(GmError::MessageBitsOutOfRange {
+                bits: 25,
+                available: 24
+            })
+        );
+    }
+
+    #[test]
+    fn test_hmac_truncate_and_verify() {
+        let full = hmac(b"key", b"message");
+        let tag = hmac_truncated(b"key", b"message", 16).unwrap();
+        assert_eq!(tag, full[..16]);
+        assert!(hmac_verify(&full, &tag).unwrap());
+        assert!(hmac_verify(&full, &full).unwrap());
+
+        let mut wrong = tag.clone();
+        wrong[15] ^= 1;
+        assert!(!hmac_verify(&full, &wrong).unwrap());
+        assert_eq!(
+            hmac_verify(&full, &tag[..9]),
+            Err(GmError::InvalidTagLength {
+                actual: 9,
+                min: 10,
+                max: 32
+            })
+        );
+
+        let progress = Progress::new(7);
+        assert_eq!(
+            hmac_reader(b"key", &b"message"[..], &progress).unwrap(),
+            full
+        );
+    }
+
+    #[test]
+    fn test_digest_reader_matches_digest() {
+        let data: Vec<u8> = (0..stream::CHUNK_SIZE + 1000).map(|i| i as u8).collect();
+        let progress = Progress::new(data.len() as u64);
+        assert_eq!(digest_reader(&data[..], &progress).unwrap(), digest(&data));
+        assert_eq!(progress.done(), data.len() as u64);
+    }
+}
@@ -0,0 +1,364 @@
+//! SM3 逐轮计算过程 (教学演示) 与按比特长度计算的摘要
+//!
+//! 独立实现 GB/T 32905-2016 的填充、消息扩展与压缩函数，可记录每一轮的中间值；
+//! 整字节消息的结果与 `sm3` crate 交叉校验。`sm3` crate 只能处理整字节，
+//! 长度不是 8 的倍数的消息 ([`crate::sm3::digest_bits`]) 也由这里计算。
+
+use std::fmt::Write as _;
+
+use crate::error::{GmError, Result};
+use crate::sm3::{BLOCK_LEN, DIGEST_LEN};
+
+/// Longest message [`trace`] accepts, so that the trace stays readable.
+pub const MAX_TRACE_LEN: usize = 4096;
+
+/// Rounds of the compression function.
+pub const ROUNDS: usize = 64;
+
+/// Initial value `IV`.
+pub const IV: [u32; 8] = [
+    0x7380166f, 0x4914b2b9, 0x172442d7, 0xda8a0600, 0xa96f30bc, 0x163138aa, 0xe38dee4d, 0xb0fb0e4e,
+];
+
+/// Register names, in the order of [`Round::regs`].
+pub const REGISTERS: [&str; 8] = ["A", "B", "C", "D", "E", "F", "G", "H"];
+
+/// Intermediate values of one compression round `j`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Round {
+    pub ss1: u32,
+    pub ss2: u32,
+    pub tt1: u32,
+    pub tt2: u32,
+    /// `A..H` after the round.
+    pub regs: [u32; 8],
+}
+
+/// Message expansion and compression of one 512-bit block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockTrace {
+    pub block: [u8; BLOCK_LEN],
+    /// `W0..W67`.
+    pub w: [u32; 68],
+    /// `W'0..W'63`.
+    pub w1: [u32; 64],
+    /// Chaining value `V(i)` going in; also `A..H` before round 0.
+    pub v_in: [u32; 8],
+    pub rounds: Vec<Round>,
+    /// `V(i+1) = ABCDEFGH ⊕ V(i)`.
+    pub v_out: [u32; 8],
+}
+
+/// Every intermediate value of hashing one message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub message: Vec<u8>,
+    /// Message length `l` in bits.
+    pub bit_len: u64,
+    /// The message after padding: `m || 1 || 0^k || l` (64-bit big endian).
+    pub padded: Vec<u8>,
+    pub blocks: Vec<BlockTrace>,
+    pub digest: [u8; DIGEST_LEN],
+}
+
+/// Hash `msg` step by step, recording every intermediate value.
+pub fn trace(msg: &[u8]) -> Result<Trace> {
+    trace_bits(msg, msg.len() * 8)
+}
+
+/// [`trace`] of the first `bits` bits of `msg`.
+pub fn trace_bits(msg: &[u8], bits: usize) -> Result<Trace> {
+    let message = leading_bits(msg, bits)?;
+    if message.len() > MAX_TRACE_LEN {
+        return Err(GmError::InputTooLong {
+            max: MAX_TRACE_LEN,
+            actual: message.len(),
+        });
+    }
+    let padded = pad(&message, bits);
+    let mut v = IV;
+    let blocks: Vec<BlockTrace> = blocks(&padded)
+        .map(|block| {
+            let mut rounds = Vec::with_capacity(ROUNDS);
+            let (w, w1, v_out) = compress(&v, block, |round| rounds.push(round));
+            let traced = BlockTrace {
+                block: *block,
+                w,
+                w1,
+                v_in: v,
+                rounds,
+                v_out,
+            };
+            v = v_out;
+            traced
+        })
+        .collect();
+
+    Ok(Trace {
+        message,
+        bit_len: bits as u64,
+        padded,
+        blocks,
+        digest: to_bytes(&v),
+    })
+}
+
+/// SM3 of the first `bits` bits of `msg`, without recording the rounds.
+pub(crate) fn digest_bits(msg: &[u8], bits: usize) -> Result<[u8; DIGEST_LEN]> {
+    let padded = pad(&leading_bits(msg, bits)?, bits);
+    let mut v = IV;
+    for block in blocks(&padded) {
+        (_, _, v) = compress(&v, block, |_| {});
+    }
+    Ok(to_bytes(&v))
+}
+
+/// The bytes holding the first `bits` bits of `msg`, with the unused low
+/// bits of a partial last byte cleared.
+fn leading_bits(msg: &[u8], bits: usize) -> Result<Vec<u8>> {
+    if bits > msg.len() * 8 {
+        return Err(GmError::MessageBitsOutOfRange {
+            bits,
+            available: msg.len() * 8,
+        });
+    }
+    let mut message = msg[..bits.div_ceil(8)].to_vec();
+    if !bits.is_multiple_of(8)
+        && let Some(last) = message.last_mut()
+    {
+        *last &= 0xff << (8 - bits % 8);
+    }
+    Ok(message)
+}
+
+/// Pad a `bits`-bit message (held MSB first in `message`): append a 1 bit,
+/// zero bits up to 448 mod 512, then `bits` as a 64-bit big endian integer.
+fn pad(message: &[u8], bits: usize) -> Vec<u8> {
+    let mut padded = message.to_vec();
+    match bits % 8 {
+        0 => padded.push(0x80),
+        used => *padded.last_mut().expect("partial byte") |= 0x80 >> used,
+    }
+    while padded.len() % BLOCK_LEN != BLOCK_LEN - 8 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&(bits as u64).to_be_bytes());
+    padded
+}
+
+fn blocks(padded: &[u8]) -> impl Iterator<Item = &[u8; BLOCK_LEN]> {
+    padded
+        .chunks_exact(BLOCK_LEN)
+        .map(|chunk| chunk.try_into().expect("64-byte chunk"))
+}
+
+fn to_bytes(v: &[u32; 8]) -> [u8; DIGEST_LEN] {
+    let mut digest = [0u8; DIGEST_LEN];
+    for (out, word) in digest.chunks_exact_mut(4).zip(v) {
+        out.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+/// Compress one block into `V(i)`, handing every round to `on_round`.
+/// Returns `W`, `W'` and `V(i+1)`.
+fn compress(
+    v: &[u32; 8],
+    block: &[u8; BLOCK_LEN],
+    mut on_round: impl FnMut(Round),
+) -> ([u32; 68], [u32; 64], [u32; 8]) {
+    let mut w = [0u32; 68];
+    for (j, word) in block.chunks_exact(4).enumerate() {
+        w[j] = u32::from_be_bytes(word.try_into().expect("4-byte word"));
+    }
+    for j in 16..68 {
+        w[j] = p1(w[j - 16] ^ w[j - 9] ^ w[j - 3].rotate_left(15))
+            ^ w[j - 13].rotate_left(7)
+            ^ w[j - 6];
+    }
+    let mut w1 = [0u32; 64];
+    for j in 0..64 {
+        w1[j] = w[j] ^ w[j + 4];
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *v;
+    for j in 0..ROUNDS {
+        let t = if j < 16 { 0x79cc4519u32 } else { 0x7a879d8a };
+        let ss1 = a
+            .rotate_left(12)
+            .wrapping_add(e)
+            .wrapping_add(t.rotate_left(j as u32 % 32))
+            .rotate_left(7);
+        let ss2 = ss1 ^ a.rotate_left(12);
+        let tt1 = ff(j, a, b, c)
+            .wrapping_add(d)
+            .wrapping_add(ss2)
+            .wrapping_add(w1[j]);
+        let tt2 = gg(j, e, f, g)
+            .wrapping_add(h)
+            .wrapping_add(ss1)
+            .wrapping_add(w[j]);
+        d = c;
+        c = b.rotate_left(9);
+        b = a;
+        a = tt1;
+        h = g;
+        g = f.rotate_left(19);
+        f = e;
+        e = p0(tt2);
+        on_round(Round {
+            ss1,
+            ss2,
+            tt1,
+            tt2,
+            regs: [a, b, c, d, e, f, g, h],
+        });
+    }
+
+    let regs = [a, b, c, d, e, f, g, h];
+    (w, w1, std::array::from_fn(|i| regs[i] ^ v[i]))
+}
+
+fn ff(j: usize, x: u32, y: u32, z: u32) -> u32 {
+    if j < 16 {
+        x ^ y ^ z
+    } else {
+        (x & y) | (x & z) | (y & z)
+    }
+}
+
+fn gg(j: usize, x: u32, y: u32, z: u32) -> u32 {
+    if j < 16 {
+        x ^ y ^ z
+    } else {
+        (x & y) | (!x & z)
+    }
+}
+
+fn p0(x: u32) -> u32 {
+    x ^ x.rotate_left(9) ^ x.rotate_left(17)
+}
+
+fn p1(x: u32) -> u32 {
+    x ^ x.rotate_left(15) ^ x.rotate_left(23)
+}
+
+/// Eight words per line as `label` rows, e.g. `W0..7: ...`.
+fn write_words(out: &mut String, label: &str, words: &[u32]) {
+    for (i, row) in words.chunks(8).enumerate() {
+        let first = i * 8;
+        let _ = write!(
+            out,
+            "{:<11}",
+            format!("{}{}..{}:", label, first, first + row.len() - 1)
+        );
+        for word in row {
+            let _ = write!(out, " {:08x}", word);
+        }
+        out.push('\n');
+    }
+}
+
+fn write_regs(out: &mut String, label: &str, regs: &[u32; 8]) {
+    let _ = write!(out, "{:<11}", label);
+    for word in regs {
+        let _ = write!(out, " {:08x}", word);
+    }
+    out.push('\n');
+}
+
+impl Trace {
+    /// The whole trace as plain text, for export.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "SM3 计算过程 (GB/T 32905-2016)");
+        let _ = writeln!(
+            out,
+            "消息 ({} 比特): {}",
+            self.bit_len,
+            hex::encode(&self.message)
+        );
+        let _ = writeln!(
+            out,
+            "填充后 ({} 字节, {} 个分组): {}",
+            self.padded.len(),
+            self.blocks.len(),
+            hex::encode(&self.padded)
+        );
+        for (i, block) in self.blocks.iter().enumerate() {
+            let _ = writeln!(out, "\n== 分组 B({}) ==", i);
+            let _ = writeln!(out, "B({}) = {}", i, hex::encode(block.block));
+            let _ = writeln!(out, "-- 消息扩展 --");
+            write_words(&mut out, "W", &block.w);
+            write_words(&mut out, "W'", &block.w1);
+            let _ = writeln!(out, "-- 压缩函数 --");
+            let _ = writeln!(
+                out,
+                "{:<11} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8}",
+                "", "A", "B", "C", "D", "E", "F", "G", "H"
+            );
+            write_regs(&mut out, &format!("V({})", i), &block.v_in);
+            for (j, round) in block.rounds.iter().enumerate() {
+                write_regs(&mut out, &format!("j={}", j), &round.regs);
+                let _ = writeln!(
+                    out,
+                    "{:<11} SS1={:08x} SS2={:08x} TT1={:08x} TT2={:08x}",
+                    "", round.ss1, round.ss2, round.tt1, round.tt2
+                );
+            }
+            write_regs(&mut out, &format!("V({})", i + 1), &block.v_out);
+        }
+        let _ = writeln!(out, "\n杂凑值: {}", hex::encode(self.digest));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sm3;
+
+    #[test]
+    fn test_trace_standard_example() {
+        // GB/T 32905-2016 Appendix A.1
+        let t = trace(b"abc").unwrap();
+        assert_eq!(t.padded.len(), 64);
+        assert_eq!(t.blocks[0].w[16], 0x9092e200);
+        assert_eq!(t.blocks[0].w1[0], 0x61626380);
+        assert_eq!(
+            t.blocks[0].rounds[0].regs,
+            [
+                0xb9edc12b, 0x7380166f, 0x29657292, 0x172442d7, 0xb2ad29f4, 0xa96f30bc, 0xc550b189,
+                0xe38dee4d
+            ]
+        );
+        assert_eq!(
+            hex::encode(t.digest),
+            "66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0"
+        );
+        assert!(t.to_text().contains("j=63"));
+    }
+
+    #[test]
+    fn test_trace_matches_sm3_crate() {
+        // Lengths around the 55/56 and 64 byte padding boundaries
+        let data: Vec<u8> = (0..200u8).map(|i| i.wrapping_mul(37)).collect();
+        for len in [0, 1, 55, 56, 63, 64, 65, 119, 120, 200] {
+            let t = trace(&data[..len]).unwrap();
+            assert_eq!(t.digest, sm3::digest(&data[..len]), "len {}", len);
+            assert_eq!(t.blocks.len(), t.padded.len() / BLOCK_LEN);
+        }
+        // 20 bits: "ab" and the high nibble of 'c', then the 1 bit in the same byte
+        let t = trace_bits(b"abc", 20).unwrap();
+        assert_eq!(&t.padded[..4], &[0x61, 0x62, 0x68, 0x00]);
+        assert_eq!(t.padded[63], 20);
+        assert_eq!(t.digest, sm3::digest_bits(b"abc", 20).unwrap());
+        assert_eq!(
+            trace(&vec![0; MAX_TRACE_LEN + 1]),
+            Err(GmError::InputTooLong {
+                max: MAX_TRACE_LEN,
+                actual: MAX_TRACE_LEN + 1
+            })
+        );
+    }
+}
@@ -0,0 +1,436 @@
+//! SM4 分组密码 (GB/T 32907)：ECB / CBC / GCM
+
+use std::io::{Read, Write};
+
+use sm4::Sm4;
+use sm4::cipher::{BlockDecrypt, BlockEncrypt, NewBlockCipher, generic_array::GenericArray};
+use sm4_gcm::{
+    Sm4GcmStreamDecryptor, Sm4GcmStreamEncryptor, Sm4Key, sm4_gcm_aad_decrypt, sm4_gcm_aad_encrypt,
+};
+
+use crate::error::{GmError, Result};
+use crate::stream::{self, Progress};
+
+/// SM4 block size in bytes.
+pub const BLOCK_SIZE: usize = 16;
+/// SM4 key size in bytes.
+pub const KEY_LEN: usize = 16;
+/// SM4-GCM tag size in bytes.
+pub const GCM_TAG_LEN: usize = 16;
+
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub enum Sm4Mode {
+    #[default]
+    CBC,
+    ECB,
+    GCM,
+}
+
+impl Sm4Mode {
+    pub const ALL: [Sm4Mode; 3] = [Sm4Mode::ECB, Sm4Mode::CBC, Sm4Mode::GCM];
+
+    /// Lower-case name used on the command line and in saved settings.
+    pub fn tag(self) -> &'static str {
+        match self {
+            Sm4Mode::ECB => "ecb",
+            Sm4Mode::CBC => "cbc",
+            Sm4Mode::GCM => "gcm",
+        }
+    }
+
+    pub fn from_tag(tag: &str) -> Option<Sm4Mode> {
+        Sm4Mode::ALL.into_iter().find(|m| m.tag() == tag)
+    }
+}
+
+pub fn ecb_encrypt(key: &[u8], data: &[u8], padding: bool) -> Result<Vec<u8>> {
+    crypt(key, None, data, true, padding)
+}
+
+pub fn ecb_decrypt(key: &[u8], data: &[u8], padding: bool) -> Result<Vec<u8>> {
+    crypt(key, None, data, false, padding)
+}
+
+pub fn cbc_encrypt(key: &[u8], iv: &[u8], data: &[u8], padding: bool) -> Result<Vec<u8>> {
+    check_iv(iv)?;
+    crypt(key, Some(iv), data, true, padding)
+}
+
+pub fn cbc_decrypt(key: &[u8], iv: &[u8], data: &[u8], padding: bool) -> Result<Vec<u8>> {
+    check_iv(iv)?;
+    crypt(key, Some(iv), data, false, padding)
+}
+
+/// SM4-GCM authenticated encryption. Output is `ciphertext || tag` (16-byte tag).
+pub fn gcm_encrypt(key: &[u8], nonce: &[u8], aad: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let key = gcm_key(key, nonce)?;
+    Ok(sm4_gcm_aad_encrypt(&key, nonce, aad, data))
+}
+
+/// SM4-GCM authenticated decryption of `ciphertext || tag`.
+pub fn gcm_decrypt(key: &[u8], nonce: &[u8], aad: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let key = gcm_key(key, nonce)?;
+    if data.len() < GCM_TAG_LEN {
+        return Err(GmError::CiphertextTooShort {
+            len: data.len(),
+            min: GCM_TAG_LEN,
+        });
+    }
+    sm4_gcm_aad_decrypt(&key, nonce, aad, data).map_err(|_| GmError::TagMismatch)
+}
+
+fn gcm_key(key: &[u8], nonce: &[u8]) -> Result<Sm4Key> {
+    check_key(key)?;
+    if nonce.is_empty() {
+        return Err(GmError::EmptyInput { field: "Nonce" });
+    }
+    Ok(Sm4Key::from_slice(key).expect("key length checked"))
+}
+
+fn check_key(key: &[u8]) -> Result<()> {
+    if key.len() != KEY_LEN {
+        return Err(GmError::InvalidKeyLength {
+            expected: KEY_LEN,
+            actual: key.len(),
+        });
+    }
+    Ok(())
+}
+
+fn check_iv(iv: &[u8]) -> Result<()> {
+    if iv.len() != BLOCK_SIZE {
+        return Err(GmError::InvalidIvLength {
+            expected: BLOCK_SIZE,
+            actual: iv.len(),
+        });
+    }
+    Ok(())
+}
+
+fn crypt(
+    key: &[u8],
+    iv: Option<&[u8]>,
+    data: &[u8],
+    encrypt: bool,
+    use_padding: bool,
+) -> Result<Vec<u8>> {
+    let mut stream = BlockStream::new(key, iv, encrypt, use_padding)?;
+    let mut output = stream.update(data);
+    output.extend(stream.finish()?);
+    Ok(output)
+}
+
+/// Incremental SM4 ECB / CBC for data that arrives in chunks of any size.
+///
+/// Feeding the whole input to [`BlockStream::update`] at once gives the same
+/// result as the one-shot `ecb_*` / `cbc_*` functions.
+pub struct BlockStream {
+    cipher: Sm4,
+    /// Previous ciphertext block (the IV at first); `None` in ECB mode.
+    chain: Option<Block>,
+    encrypt: bool,
+    use_padding: bool,
+    /// Input not yet processed: an incomplete block, plus the last full block
+    /// when decrypting with padding (it can only be unpadded in `finish`).
+    pending: Vec<u8>,
+    total: usize,
+}
+
+type Block = GenericArray<u8, sm4::cipher::consts::U16>;
+
+impl BlockStream {
+    /// ECB when `iv` is `None`, CBC otherwise.
+    pub fn new(key: &[u8], iv: Option<&[u8]>, encrypt: bool, use_padding: bool) -> Result<Self> {
+        check_key(key)?;
+        if let Some(iv) = iv {
+            check_iv(iv)?;
+        }
+        Ok(Self {
+            cipher: Sm4::new(GenericArray::from_slice(key)),
+            chain: iv.map(GenericArray::clone_from_slice),
+            encrypt,
+            use_padding,
+            pending: Vec::with_capacity(BLOCK_SIZE),
+            total: 0,
+        })
+    }
+
+    /// Process `data`, returning the output for every block that is now complete.
+    pub fn update(&mut self, data: &[u8]) -> Vec<u8> {
+        self.total += data.len();
+        self.pending.extend_from_slice(data);
+
+        let mut ready = self.pending.len() / BLOCK_SIZE * BLOCK_SIZE;
+        if !self.encrypt && self.use_padding && ready == self.pending.len() {
+            ready = ready.saturating_sub(BLOCK_SIZE);
+        }
+        let mut output = self.pending[..ready].to_vec();
+        for block in output.chunks_exact_mut(BLOCK_SIZE) {
+            self.process_block(GenericArray::from_mut_slice(block));
+        }
+        self.pending.drain(..ready);
+        output
+    }
+
+    /// Process the remaining input, applying or removing PKCS#7 padding.
+    pub fn finish(mut self) -> Result<Vec<u8>> {
+        let mut last = std::mem::take(&mut self.pending);
+        if self.encrypt && self.use_padding {
+            // Padding (PKCS7)
+            let padding_len = BLOCK_SIZE - last.len();
+            last.extend(std::iter::repeat_n(padding_len as u8, padding_len));
+        } else if !last.len().is_multiple_of(BLOCK_SIZE) {
+            return Err(GmError::InvalidDataLength {
+                block_size: BLOCK_SIZE,
+                actual: self.total,
+            });
+        }
+
+        for block in last.chunks_exact_mut(BLOCK_SIZE) {
+            self.process_block(GenericArray::from_mut_slice(block));
+        }
+
+        if !self.encrypt && self.use_padding {
+            // Unpad (PKCS7); `last` is empty or exactly one block here
+            if let Some(&pad) = last.last() {
+                let pad_len = pad as usize;
+                let pad_start = last.len().wrapping_sub(pad_len);
+                if pad_len == 0
+                    || pad_len > BLOCK_SIZE
+                    || !last[pad_start..].iter().all(|&b| b == pad)
+                {
+                    return Err(GmError::PaddingInvalid);
+                }
+                last.truncate(pad_start);
+            }
+        }
+        Ok(last)
+    }
+
+    fn process_block(&mut self, block: &mut Block) {
+        match (&mut self.chain, self.encrypt) {
+            (Some(chain), true) => {
+                // CBC Encrypt: XOR with previous ciphertext, then encrypt
+                for (b, c) in block.iter_mut().zip(chain.iter()) {
+                    *b ^= *c;
+                }
+                self.cipher.encrypt_block(block);
+                *chain = *block;
+            }
+            (Some(chain), false) => {
+                // CBC Decrypt: decrypt, then XOR with previous ciphertext
+                let ciphertext = *block;
+                self.cipher.decrypt_block(block);
+                for (b, c) in block.iter_mut().zip(chain.iter()) {
+                    *b ^= *c;
+                }
+                *chain = ciphertext;
+            }
+            (None, true) => self.cipher.encrypt_block(block),
+            (None, false) => self.cipher.decrypt_block(block),
+        }
+    }
+}
+
+/// Stream `reader` through SM4 into `writer` in [`stream::CHUNK_SIZE`] pieces.
+///
+/// `iv` is ignored in ECB mode and is the nonce in GCM mode. GCM decryption
+/// writes plaintext before the tag has been checked: on
+/// [`GmError::TagMismatch`] the caller must discard everything written.
+#[allow(clippy::too_many_arguments)]
+pub fn crypt_stream(
+    mode: Sm4Mode,
+    key: &[u8],
+    iv: &[u8],
+    aad: &[u8],
+    encrypt: bool,
+    use_padding: bool,
+    reader: impl Read,
+    mut writer: impl Write,
+    progress: &Progress,
+) -> Result<()> {
+    match mode {
+        Sm4Mode::ECB | Sm4Mode::CBC => {
+            let iv = (mode == Sm4Mode::CBC).then_some(iv);
+            let mut blocks = BlockStream::new(key, iv, encrypt, use_padding)?;
+            stream::for_each_chunk(reader, progress, |chunk| {
+                stream::write_all(&mut writer, &blocks.update(chunk))
+            })?;
+            stream::write_all(&mut writer, &blocks.finish()?)?;
+        }
+        Sm4Mode::GCM if encrypt => {
+            let mut gcm = Sm4GcmStreamEncryptor::new(&gcm_key(key, iv)?, iv);
+            gcm.init_adata(aad);
+            stream::for_each_chunk(reader, progress, |chunk| {
+                stream::write_all(&mut writer, &gcm.update(chunk))
+            })?;
+            let (tail, tag) = gcm.finalize();
+            stream::write_all(&mut writer, &tail)?;
+            stream::write_all(&mut writer, &tag)?;
+        }
+        Sm4Mode::GCM => {
+            let mut gcm = Sm4GcmStreamDecryptor::new(&gcm_key(key, iv)?, iv);
+            gcm.init_adata(aad);
+            let mut total = 0;
+            stream::for_each_chunk(reader, progress, |chunk| {
+                total += chunk.len();
+                stream::write_all(&mut writer, &gcm.update(chunk))
+            })?;
+            if total < GCM_TAG_LEN {
+                return Err(GmError::CiphertextTooShort {
+                    len: total,
+                    min: GCM_TAG_LEN,
+                });
+            }
+            let tail = gcm.finalize().map_err(|_| GmError::TagMismatch)?;
+            stream::write_all(&mut writer, &tail)?;
+        }
+    }
+    stream::flush(&mut writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ecb_standard_vector() {
+        // GB/T 32907 Appendix A.1
+        let key = hex::decode("0123456789abcdeffedcba9876543210").unwrap();
+        let ct = ecb_encrypt(&key, &key, false).unwrap();
+        assert_eq!(hex::encode(&ct), "681edf34d206965e86b3e94f536e4246");
+        assert_eq!(ecb_decrypt(&key, &ct, false).unwrap(), key);
+    }
+
+    #[test]
+    fn test_cbc_padding_roundtrip() {
+        let key = [0x11u8; 16];
+        let iv = [0x22u8; 16];
+        for len in [0usize, 1, 15, 16, 17, 33] {
+            let pt = vec![0xA5u8; len];
+            let ct = cbc_encrypt(&key, &iv, &pt, true).unwrap();
+            assert_eq!(ct.len(), (len / 16 + 1) * 16);
+            assert_eq!(cbc_decrypt(&key, &iv, &ct, true).unwrap(), pt);
+        }
+    }
+
+    #[test]
+    fn test_block_stream_matches_one_shot() {
+        let key = [0x33u8; 16];
+        let iv = [0x44u8; 16];
+        let pt: Vec<u8> = (0..1000u32).map(|i| (i * 13) as u8).collect();
+        for (cbc, padding) in [(false, true), (true, true), (true, false)] {
+            let pt = if padding { &pt[..] } else { &pt[..992] };
+            let iv = cbc.then_some(&iv[..]);
+            let ct = crypt(&key, iv, pt, true, padding).unwrap();
+
+            for (encrypt, input, expected) in [(true, pt, &ct[..]), (false, &ct[..], pt)] {
+                let mut stream = BlockStream::new(&key, iv, encrypt, padding).unwrap();
+                let mut out = Vec::new();
+                for chunk in input.chunks(37) {
+                    out.extend(stream.update(chunk));
+                }
+                out.extend(stream.finish().unwrap());
+                assert_eq!(out, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_gcm_stream_matches_one_shot() {
+        let key = [0x55u8; 16];
+        let nonce = [0x66u8; 12];
+        let pt: Vec<u8> = (0..stream::CHUNK_SIZE + 21).map(|i| i as u8).collect();
+        let ct = gcm_encrypt(&key, &nonce, b"aad", &pt).unwrap();
+
+        let mut out = Vec::new();
+        let progress = Progress::new(0);
+        crypt_stream(
+            Sm4Mode::GCM,
+            &key,
+            &nonce,
+            b"aad",
+            true,
+            true,
+            &pt[..],
+            &mut out,
+            &progress,
+        )
+        .unwrap();
+        assert_eq!(out, ct);
+
+        let mut out = Vec::new();
+        crypt_stream(
+            Sm4Mode::GCM,
+            &key,
+            &nonce,
+            b"aad",
+            false,
+            true,
+            &ct[..],
+            &mut out,
+            &progress,
+        )
+        .unwrap();
+        assert_eq!(out, pt);
+
+        let mut tampered = ct.clone();
+        tampered[0] ^= 1;
+        assert_eq!(
+            crypt_stream(
+                Sm4Mode::GCM,
+                &key,
+                &nonce,
+                b"aad",
+                false,
+                true,
+                &tampered[..],
+                Vec::new(),
+                &progress
+            ),
+            Err(GmError::TagMismatch)
+        );
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let key = [0u8; 16];
+        assert_eq!(
+            ecb_encrypt(&key[..15], b"", true),
+            Err(GmError::InvalidKeyLength {
+                expected: 16,
+                actual: 15
+            })
+        );
+        assert_eq!(
+            cbc_encrypt(&key, &[0u8; 12], b"", true),
+            Err(GmError::InvalidIvLength {
+                expected: 16,
+                actual: 12
+            })
+        );
+        assert_eq!(
+            ecb_encrypt(&key, &[0u8; 17], false),
+            Err(GmError::InvalidDataLength {
+                block_size: 16,
+                actual: 17
+            })
+        );
+        // Last plaintext byte 0x00 is never valid PKCS#7 padding
+        let ct = ecb_encrypt(&key, &[0u8; 16], false).unwrap();
+        assert_eq!(ecb_decrypt(&key, &ct, true), Err(GmError::PaddingInvalid));
+
+        let nonce = [0u8; 12];
+        let mut ct = gcm_encrypt(&key, &nonce, b"aad", b"hello").unwrap();
+        assert_eq!(gcm_decrypt(&key, &nonce, b"aad", &ct).unwrap(), b"hello");
+        *ct.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            gcm_decrypt(&key, &nonce, b"aad", &ct),
+            Err(GmError::TagMismatch)
+        );
+        assert_eq!(
+            gcm_encrypt(&key, &[], b"", b""),
+            Err(GmError::EmptyInput { field: "Nonce" })
+        );
+    }
+}
@@ -0,0 +1,211 @@
+//! 大文件的分块流式处理：进度与取消
+
+use std::fs::File;
+use std::io::{BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use crate::error::{GmError, Result};
+
+/// Size of the pieces streaming operations read at a time.
+pub const CHUNK_SIZE: usize = 1 << 20;
+
+/// Progress of a streaming operation, shared between the worker and the UI.
+#[derive(Debug, Default)]
+pub struct Progress {
+    done: AtomicU64,
+    total: AtomicU64,
+    cancelled: AtomicBool,
+}
+
+impl Progress {
+    /// `total` is the expected number of input bytes, or 0 if unknown.
+    pub fn new(total: u64) -> Self {
+        Self {
+            total: AtomicU64::new(total),
+            ..Self::default()
+        }
+    }
+
+    pub fn done(&self) -> u64 {
+        self.done.load(Ordering::Relaxed)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    pub fn set_total(&self, total: u64) {
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    /// Fraction of the input processed so far, in `0.0..=1.0`.
+    pub fn fraction(&self) -> f32 {
+        match self.total() {
+            0 => 0.0,
+            total => (self.done() as f64 / total as f64).min(1.0) as f32,
+        }
+    }
+
+    /// Ask the operation to stop at the next chunk boundary.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Record `n` more processed bytes, failing with [`GmError::Cancelled`] once cancelled.
+    pub fn advance(&self, n: usize) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(GmError::Cancelled);
+        }
+        self.done.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Read `reader` to the end in pieces of at most [`CHUNK_SIZE`] bytes, passing each to `f`.
+pub fn for_each_chunk(
+    mut reader: impl Read,
+    progress: &Progress,
+    mut f: impl FnMut(&[u8]) -> Result<()>,
+) -> Result<()> {
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(GmError::Stream(e.to_string())),
+        };
+        f(&buf[..n])?;
+        progress.advance(n)?;
+    }
+}
+
+pub(crate) fn write_all(writer: &mut impl Write, data: &[u8]) -> Result<()> {
+    writer
+        .write_all(data)
+        .map_err(|e| GmError::Stream(e.to_string()))
+}
+
+pub(crate) fn flush(writer: &mut impl Write) -> Result<()> {
+    writer.flush().map_err(|e| GmError::Stream(e.to_string()))
+}
+
+/// Open `path` for a streaming read, setting the progress total to its size.
+pub fn open_input(path: &Path, progress: &Progress) -> Result<File> {
+    let file = File::open(path).map_err(|e| io_error(path, e))?;
+    if let Ok(meta) = file.metadata() {
+        progress.set_total(meta.len());
+    }
+    Ok(file)
+}
+
+/// Stream the file `input` into the file `output` with `f`, returning the output size.
+///
+/// The output is written to a temporary file next to `output` and renamed
+/// over it only on success: on failure or cancellation nothing unauthenticated
+/// or truncated is left behind and an existing `output` is kept, and `output`
+/// may be `input` itself.
+pub fn file_to_file(
+    input: &Path,
+    output: &Path,
+    progress: &Progress,
+    f: impl FnOnce(File, &mut BufWriter<File>, &Progress) -> Result<()>,
+) -> Result<u64> {
+    let reader = open_input(input, progress)?;
+    let temp = temp_path(output);
+    let file = File::create(&temp).map_err(|e| io_error(output, e))?;
+    let mut writer = BufWriter::with_capacity(CHUNK_SIZE, file);
+    let result = f(reader, &mut writer, progress)
+        .and_then(|()| flush(&mut writer))
+        .and_then(|()| writer.get_ref().metadata().map_err(|e| io_error(output, e)))
+        .map(|meta| meta.len());
+    drop(writer);
+    let result = result.and_then(|len| {
+        std::fs::rename(&temp, output).map_err(|e| io_error(output, e))?;
+        Ok(len)
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result
+}
+
+/// A fresh hidden file name in the directory of `path`, so that renaming it
+/// over `path` does not cross file systems.
+fn temp_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(
+        ".{}.{}-{}.tmp",
+        name,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// [`GmError::Io`] for a failed read or write of `path`.
+pub fn io_error(path: &Path, e: std::io::Error) -> GmError {
+    GmError::Io {
+        path: path.display().to_string(),
+        detail: e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_progress_and_cancel() {
+        let data = vec![7u8; CHUNK_SIZE * 2 + 5];
+        let progress = Progress::new(data.len() as u64);
+        let mut sizes = Vec::new();
+        for_each_chunk(&data[..], &progress, |chunk| {
+            sizes.push(chunk.len());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(sizes.iter().sum::<usize>(), data.len());
+        assert_eq!(progress.fraction(), 1.0);
+
+        let progress = Progress::new(data.len() as u64);
+        let result = for_each_chunk(&data[..], &progress, |_| {
+            progress.cancel();
+            Ok(())
+        });
+        assert_eq!(result, Err(GmError::Cancelled));
+        assert_eq!(progress.done(), 0);
+    }
+
+    #[test]
+    fn test_file_to_file_in_place_and_on_failure() {
+        let dir = std::env::temp_dir().join(format!("gm_tools_stream_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.bin");
+        std::fs::write(&path, b"plaintext of 24 bytes!!!").unwrap();
+
+        // Output and input are the same file: the whole input is still read
+        let upper = |r: File, w: &mut BufWriter<File>, p: &Progress| {
+            for_each_chunk(r, p, |chunk| write_all(w, &chunk.to_ascii_uppercase()))
+        };
+        let written = file_to_file(&path, &path, &Progress::default(), upper).unwrap();
+        assert_eq!(written, 24);
+        assert_eq!(std::fs::read(&path).unwrap(), b"PLAINTEXT OF 24 BYTES!!!");
+
+        // A failed run keeps the existing output and leaves no temporary file
+        let result = file_to_file(&path, &path, &Progress::default(), |_, w, _| {
+            write_all(w, b"partial")?;
+            Err(GmError::Cancelled)
+        });
+        assert_eq!(result, Err(GmError::Cancelled));
+        assert_eq!(std::fs::read(&path).unwrap(), b"PLAINTEXT OF 24 BYTES!!!");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
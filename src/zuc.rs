@@ -0,0 +1,236 @@
+//! ZUC 祖冲之序列密码：ZUC-128 / ZUC-256 / ZUC-256 新初始化方案
+
+use std::io::{Read, Write};
+
+use zuc::cipher::generic_array::GenericArray;
+use zuc::cipher::{KeyIvInit, StreamCipher};
+use zuc::zuc128::Zuc128StreamCipher;
+use zuc::zuc256::Zuc256StreamCipher;
+
+use crate::error::{GmError, Result};
+use crate::stream::{self, Progress};
+pub use crate::zuc256_new::{Zuc256Mode, Zuc256NewStreamCipher};
+
+#[derive(PartialEq, Eq, Default, Clone, Copy, Debug)]
+pub enum ZucVariant {
+    #[default]
+    Zuc128,
+    Zuc256,
+    /// ZUC-256 with the new initialization scheme (128-bit IV).
+    Zuc256New,
+}
+
+impl ZucVariant {
+    pub fn key_len(self) -> usize {
+        match self {
+            ZucVariant::Zuc128 => 16,
+            ZucVariant::Zuc256 | ZucVariant::Zuc256New => 32,
+        }
+    }
+
+    pub fn iv_len(self) -> usize {
+        match self {
+            ZucVariant::Zuc128 | ZucVariant::Zuc256New => 16,
+            ZucVariant::Zuc256 => 23,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Default, Clone, Copy, Debug)]
+pub enum MacLen {
+    #[default]
+    L32 = 32,
+    L64 = 64,
+    L128 = 128,
+}
+
+impl MacLen {
+    pub fn bits(self) -> usize {
+        self as usize
+    }
+
+    pub fn from_bits(bits: usize) -> Option<MacLen> {
+        [MacLen::L32, MacLen::L64, MacLen::L128]
+            .into_iter()
+            .find(|m| m.bits() == bits)
+    }
+
+    fn mode(self) -> Zuc256Mode {
+        match self {
+            MacLen::L32 => Zuc256Mode::Mac32,
+            MacLen::L64 => Zuc256Mode::Mac64,
+            MacLen::L128 => Zuc256Mode::Mac128,
+        }
+    }
+}
+
+/// XOR the keystream of the chosen ZUC variant into `data`.
+/// Encryption and decryption are the same operation.
+pub fn apply_keystream(variant: ZucVariant, key: &[u8], iv: &[u8], data: &mut [u8]) -> Result<()> {
+    ZucStream::new(variant, key, iv)?.apply_keystream(data);
+    Ok(())
+}
+
+/// Keystream of any ZUC variant that continues across calls, for data
+/// processed in chunks.
+pub enum ZucStream {
+    Zuc128(Zuc128StreamCipher),
+    Zuc256(Zuc256StreamCipher),
+    Zuc256New(Zuc256NewStreamCipher),
+}
+
+impl ZucStream {
+    pub fn new(variant: ZucVariant, key: &[u8], iv: &[u8]) -> Result<Self> {
+        check_params(variant, key, iv)?;
+        Ok(match variant {
+            ZucVariant::Zuc128 => ZucStream::Zuc128(Zuc128StreamCipher::new(
+                GenericArray::from_slice(key),
+                GenericArray::from_slice(iv),
+            )),
+            ZucVariant::Zuc256 => ZucStream::Zuc256(Zuc256StreamCipher::new(
+                GenericArray::from_slice(key),
+                GenericArray::from_slice(iv),
+            )),
+            ZucVariant::Zuc256New => {
+                ZucStream::Zuc256New(Zuc256NewStreamCipher::new(key, iv, Zuc256Mode::Encrypt))
+            }
+        })
+    }
+
+    pub fn apply_keystream(&mut self, data: &mut [u8]) {
+        match self {
+            ZucStream::Zuc128(cipher) => cipher.apply_keystream(data),
+            ZucStream::Zuc256(cipher) => cipher.apply_keystream(data),
+            ZucStream::Zuc256New(cipher) => cipher.apply_keystream(data),
+        }
+    }
+}
+
+/// Stream `reader` through the ZUC keystream into `writer` in [`stream::CHUNK_SIZE`] pieces.
+pub fn apply_keystream_stream(
+    variant: ZucVariant,
+    key: &[u8],
+    iv: &[u8],
+    reader: impl Read,
+    mut writer: impl Write,
+    progress: &Progress,
+) -> Result<()> {
+    let mut cipher = ZucStream::new(variant, key, iv)?;
+    let mut buf = Vec::with_capacity(stream::CHUNK_SIZE);
+    stream::for_each_chunk(reader, progress, |chunk| {
+        buf.clear();
+        buf.extend_from_slice(chunk);
+        cipher.apply_keystream(&mut buf);
+        stream::write_all(&mut writer, &buf)
+    })?;
+    stream::flush(&mut writer)
+}
+
+/// Generate a ZUC-256 (new initialization scheme) MAC over the first `msg_bits` bits of `msg`.
+pub fn zuc256_mac(
+    key: &[u8],
+    iv: &[u8],
+    msg: &[u8],
+    msg_bits: usize,
+    mac_len: MacLen,
+) -> Result<Vec<u8>> {
+    check_params(ZucVariant::Zuc256New, key, iv)?;
+    if msg_bits > msg.len() * 8 {
+        return Err(GmError::MessageBitsOutOfRange {
+            bits: msg_bits,
+            available: msg.len() * 8,
+        });
+    }
+    let mut cipher = Zuc256NewStreamCipher::new(key, iv, mac_len.mode());
+    Ok(cipher.generate_mac(msg, msg_bits, mac_len.bits()))
+}
+
+fn check_params(variant: ZucVariant, key: &[u8], iv: &[u8]) -> Result<()> {
+    if key.len() != variant.key_len() {
+        return Err(GmError::InvalidKeyLength {
+            expected: variant.key_len(),
+            actual: key.len(),
+        });
+    }
+    if iv.len() != variant.iv_len() {
+        return Err(GmError::InvalidIvLength {
+            expected: variant.iv_len(),
+            actual: iv.len(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zuc128_eea3_keystream() {
+        // ZUC-128 test set 1 (all-zero key/IV): z1 = 27bede74, z2 = 018082da
+        let mut data = [0u8; 8];
+        apply_keystream(ZucVariant::Zuc128, &[0u8; 16], &[0u8; 16], &mut data).unwrap();
+        assert_eq!(hex::encode(data), "27bede74018082da");
+    }
+
+    #[test]
+    fn test_stream_matches_one_shot_across_odd_chunks() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 7) as u8).collect();
+        for variant in [
+            ZucVariant::Zuc128,
+            ZucVariant::Zuc256,
+            ZucVariant::Zuc256New,
+        ] {
+            let key = vec![0x3Cu8; variant.key_len()];
+            let iv = vec![0xC3u8; variant.iv_len()];
+            let mut expected = data.clone();
+            apply_keystream(variant, &key, &iv, &mut expected).unwrap();
+
+            let mut cipher = ZucStream::new(variant, &key, &iv).unwrap();
+            let mut chunked = data.clone();
+            let mut rest = &mut chunked[..];
+            for size in [1usize, 3, 5, 2, 7, 4, 13].iter().cycle() {
+                let take = (*size).min(rest.len());
+                let (head, tail) = rest.split_at_mut(take);
+                cipher.apply_keystream(head);
+                rest = tail;
+                if rest.is_empty() {
+                    break;
+                }
+            }
+            assert_eq!(chunked, expected, "{:?}", variant);
+
+            let mut out = Vec::new();
+            let progress = Progress::new(0);
+            apply_keystream_stream(variant, &key, &iv, &data[..], &mut out, &progress).unwrap();
+            assert_eq!(out, expected, "{:?}", variant);
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_and_param_checks() {
+        let key = [0x5Au8; 32];
+        let iv = [0xA5u8; 16];
+        let pt = b"ZUC-256 new init roundtrip".to_vec();
+        let mut buf = pt.clone();
+        apply_keystream(ZucVariant::Zuc256New, &key, &iv, &mut buf).unwrap();
+        assert_ne!(buf, pt);
+        apply_keystream(ZucVariant::Zuc256New, &key, &iv, &mut buf).unwrap();
+        assert_eq!(buf, pt);
+
+        assert_eq!(
+            apply_keystream(ZucVariant::Zuc256, &key, &iv, &mut buf),
+            Err(GmError::InvalidIvLength {
+                expected: 23,
+                actual: 16
+            })
+        );
+        assert_eq!(
+            zuc256_mac(&key, &iv, &pt, pt.len() * 8 + 1, MacLen::L32),
+            Err(GmError::MessageBitsOutOfRange {
+                bits: pt.len() * 8 + 1,
+                available: pt.len() * 8
+            })
+        );
+    }
+}
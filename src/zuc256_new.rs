@@ -73,6 +73,10 @@ pub struct Zuc256NewStreamCipher {
     lfsr: [u32; 16],
     r1: u32,
     r2: u32,
+    // Keystream bytes of the last word not yet used by apply_keystream
+    // (big-endian order, consumed from keystream[used..]).
+    keystream: [u8; 4],
+    used: usize,
 }
 
 impl Zuc256NewStreamCipher {
@@ -87,6 +91,8 @@ impl Zuc256NewStreamCipher {
             lfsr: [0; 16],
             r1: 0,
             r2: 0,
+            keystream: [0; 4],
+            used: 4,
         };
         z.init(key, iv, mode);
         z
@@ -140,28 +146,32 @@ impl Zuc256NewStreamCipher {
         self.gen_word();
     }
 
+    /// XOR the keystream into `data`.
+    /// Successive calls continue the same keystream, so data may be split
+    /// into chunks of any length.
     pub fn apply_keystream(&mut self, data: &mut [u8]) {
-        let len = data.len();
-        let chunks = len / 4;
-        let remainder = len % 4;
+        // Finish the word left over from the previous call first
+        let carried = (4 - self.used).min(data.len());
+        for b in &mut data[..carried] {
+            *b ^= self.keystream[self.used];
+            self.used += 1;
+        }
 
-        for i in 0..chunks {
-            let z = self.gen_word();
-            let offset = i * 4;
-            
-            data[offset] ^= (z >> 24) as u8;
-            data[offset + 1] ^= (z >> 16) as u8;
-            data[offset + 2] ^= (z >> 8) as u8;
-            data[offset + 3] ^= z as u8;
+        let mut words = data[carried..].chunks_exact_mut(4);
+        for word in &mut words {
+            let z = self.gen_word().to_be_bytes();
+            for (b, k) in word.iter_mut().zip(z) {
+                *b ^= k;
+            }
         }
 
-        if remainder > 0 {
-            let z = self.gen_word();
-            let offset = chunks * 4;
-            for j in 0..remainder {
-                let b = (z >> (24 - 8*j)) as u8;
-                data[offset + j] ^= b;
+        let remainder = words.into_remainder();
+        if !remainder.is_empty() {
+            self.keystream = self.gen_word().to_be_bytes();
+            for (b, k) in remainder.iter_mut().zip(self.keystream) {
+                *b ^= k;
             }
+            self.used = remainder.len();
         }
     }
 
@@ -175,7 +185,7 @@ impl Zuc256NewStreamCipher {
         let t = tag_len_bits;
         let l = msg_bits;
         let l_plus_2t = l + 2 * t;
-        let word_count = (l_plus_2t + 31) / 32;
+        let word_count = l_plus_2t.div_ceil(32);
 
         let mut keystream = Vec::with_capacity(word_count * 4);
         for _ in 0..word_count {
@@ -186,7 +196,7 @@ impl Zuc256NewStreamCipher {
         // Bit extraction helper
         // Get t bits starting from bit_index
         let get_w = |start_bit: usize, len: usize| -> Vec<u8> {
-            let mut res = vec![0u8; (len + 7) / 8];
+            let mut res = vec![0u8; len.div_ceil(8)];
             for i in 0..len {
                  let bit_idx = start_bit + i;
                  let byte_idx = bit_idx / 8;
@@ -209,13 +219,11 @@ impl Zuc256NewStreamCipher {
             // Check m_i
             let byte_idx = i / 8;
             let bit_in_byte = 7 - (i % 8);
-            if byte_idx < msg.len() {
-                if (msg[byte_idx] >> bit_in_byte) & 1 == 1 {
-                     let w_i = get_w(t + i, t);
-                     // Tag ^= W_i
-                     for (tb, wb) in tag.iter_mut().zip(w_i.iter()) {
-                         *tb ^= *wb;
-                     }
+            if byte_idx < msg.len() && (msg[byte_idx] >> bit_in_byte) & 1 == 1 {
+                let w_i = get_w(t + i, t);
+                // Tag ^= W_i
+                for (tb, wb) in tag.iter_mut().zip(w_i.iter()) {
+                    *tb ^= *wb;
                 }
             }
         }
@@ -304,8 +312,7 @@ impl Zuc256NewStreamCipher {
     fn add_mod31(&self, a: u32, b: u32) -> u32 {
         let v = a.wrapping_add(b);
         let v = (v & 0x7FFFFFFF) + (v >> 31);
-        let v = (v & 0x7FFFFFFF) + (v >> 31);
-        v
+        (v & 0x7FFFFFFF) + (v >> 31)
     }
 
     fn update_lfsr(&mut self, s16: u32) {
@@ -321,7 +328,7 @@ impl Zuc256NewStreamCipher {
 }
 
 // Helpers
-fn rot(a: u32, k: u32) -> u32 { (a << k) | (a >> (32 - k)) }
+fn rot(a: u32, k: u32) -> u32 { a.rotate_left(k) }
 fn l1(x: u32) -> u32 { x ^ rot(x, 2) ^ rot(x, 10) ^ rot(x, 18) ^ rot(x, 24) }
 fn l2(x: u32) -> u32 { x ^ rot(x, 8) ^ rot(x, 14) ^ rot(x, 22) ^ rot(x, 30) }
 
@@ -0,0 +1,130 @@
+//! `gm` 命令行的输出与退出码：0 成功，1 运算失败，2 参数错误
+
+use std::process::Command;
+
+const D: &str = "3945208F7B2144B13F36E38AC6D39F95889393692860B51A42FB81EF4DF7C5B8";
+const PUB: &str = "0409F9DF311E5421A150DD7D161E4BC5C672179FAD1833FC076BB08FF356F35020\
+                   CCEA490CE26775A52DC6EA718CC1AA600AED05FBF35E084A6632F6072DA9AD13";
+
+/// Exit code, stdout and stderr of `gm args..`.
+fn gm(args: &[&str]) -> (i32, String, String) {
+    let out = Command::new(env!("CARGO_BIN_EXE_gm"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        out.status.code().unwrap(),
+        String::from_utf8_lossy(&out.stdout).into_owned(),
+        String::from_utf8_lossy(&out.stderr).into_owned(),
+    )
+}
+
+/// The words of a command line without quoted arguments.
+fn split(line: &str) -> Vec<&str> {
+    line.split_whitespace().collect()
+}
+
+/// stdout of a run that must succeed, without the trailing newline.
+fn gm_ok(args: &[&str]) -> String {
+    let (code, stdout, stderr) = gm(args);
+    assert_eq!(code, 0, "gm {:?}: {}", args, stderr);
+    stdout.trim_end().to_string()
+}
+
+#[test]
+fn test_sm3() {
+    assert_eq!(
+        gm_ok(&["sm3", "--in", "text:abc"]),
+        "66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0"
+    );
+    assert_eq!(
+        gm_ok(&["sm3", "--in", "616263", "--format", "b64"]),
+        "Zsfw9GLu7dnR8tRr3BDk4kFnxIdc8veiKX2gK49LqOA="
+    );
+
+    let (code, stdout, stderr) = gm(&["sm3", "--in", "text:abc", "--ouput", "digest.bin"]);
+    assert_eq!((code, stdout.as_str()), (2, ""));
+    assert!(stderr.contains("sm3 不支持参数 --ouput"), "{}", stderr);
+    let (code, _, stderr) = gm(&["sm3", "--in", "text:a", "--in", "text:b"]);
+    assert_eq!(code, 2);
+    assert!(stderr.contains("参数 --in 重复出现"), "{}", stderr);
+    assert_eq!(gm(&["sm3", "--in", "xyz"]).0, 2);
+    // A value that is well formed but cannot be read is a failure, not a usage error
+    let (code, _, stderr) = gm(&["sm3", "--in", "file:/nonexistent/gm_cli_input"]);
+    assert_eq!(code, 1);
+    assert!(!stderr.contains("用法"), "{}", stderr);
+}
+
+#[test]
+fn test_sm4_gcm_tampered() {
+    let key = "0123456789abcdeffedcba9876543210";
+    let iv = "00001234567800000000abcd";
+    let gcm = |action: &str, data: &str| {
+        gm(&[
+            "sm4", action, "--mode", "gcm", "--key", key, "--iv", iv, "--aad", "text:hdr", "--in",
+            data,
+        ])
+    };
+    let (code, ciphertext, stderr) = gcm("encrypt", "text:hello gm");
+    assert_eq!(code, 0, "{}", stderr);
+    let ciphertext = ciphertext.trim_end();
+    assert_eq!(ciphertext.len(), 2 * (8 + 16));
+    let (code, plaintext, _) = gcm("decrypt", ciphertext);
+    assert_eq!((code, plaintext.as_str()), (0, "68656c6c6f20676d\n"));
+
+    let mut tampered = ciphertext.to_string();
+    let flipped = if tampered.starts_with('0') { "1" } else { "0" };
+    tampered.replace_range(..1, flipped);
+    let (code, stdout, _) = gcm("decrypt", &tampered);
+    assert_eq!((code, stdout.as_str()), (1, ""));
+    assert_eq!(gm(&["sm4", "encrypt", "--mode", "xts"]).0, 2);
+}
+
+#[test]
+fn test_sm2_verify() {
+    let sig = gm_ok(&["sm2", "sign", "--pri", D, "--in", "text:message digest"]);
+    let verify = |msg: &str| gm(&["sm2", "verify", "--pub", PUB, "--in", msg, "--sig", &sig]);
+    let (code, stdout, _) = verify("text:message digest");
+    assert_eq!((code, stdout.as_str()), (0, "验签结果: 通过 (Valid)\n"));
+    let (code, _, stderr) = verify("text:message digesT");
+    assert_eq!(code, 1);
+    assert!(stderr.contains("验签结果: 失败"), "{}", stderr);
+
+    // A well-formed key that is out of range is a failure
+    let n = "FFFFFFFEFFFFFFFFFFFFFFFFFFFFFFFF7203DF6B21C6052B53BBF40939D54123";
+    assert_eq!(gm(&["sm2", "pubkey", "--pri", n]).0, 1);
+    assert_eq!(gm(&["sm2", "verify", "--pub", PUB, "--in", "00"]).0, 2);
+}
+
+#[test]
+fn test_zuc() {
+    let zeros = "00000000000000000000000000000000";
+    let args = format!(
+        "zuc encrypt --variant 128 --key {0} --iv {0} --in {1}",
+        zeros,
+        &zeros[..16]
+    );
+    let keystream = gm_ok(&split(&args));
+    assert_eq!(keystream, "27bede74018082da");
+    assert_eq!(gm(&["zuc", "encrypt", "--variant", "512"]).0, 2);
+    assert_eq!(gm(&["zuc", "rekey"]).0, 2);
+}
+
+#[test]
+fn test_kdf() {
+    assert_eq!(
+        gm_ok(&split(
+            "kdf pbkdf2 --in text:password --salt text:salt --iter 2 --len 32"
+        )),
+        "fee723a2bc966e11dffb66133f4e8df577383c78ade30e3298edbd3e54ed85b7"
+    );
+    let (code, _, stderr) = gm(&split("kdf sm2 --in 00 --len 1000000"));
+    assert_eq!(code, 1);
+    assert!(stderr.contains("1000000"), "{}", stderr);
+    // Refused up front instead of running practically forever
+    let (code, _, stderr) = gm(&split("kdf pbkdf2 --in text:x --iter 4000000000"));
+    assert_eq!(code, 1);
+    assert!(stderr.contains("4000000000"), "{}", stderr);
+    // --salt means nothing to the SM2 KDF
+    assert_eq!(gm(&split("kdf sm2 --in 00 --salt 00")).0, 2);
+}